@@ -0,0 +1,95 @@
+use crate::LogicalBlockSize;
+
+const MIB: u64 = 1024 * 1024;
+
+/// Alignment of partition boundaries, in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Alignment(u64);
+
+impl Alignment {
+    /// 1 MiB, the default of most partitioning tools.
+    pub const MIB: Self = Self(MIB);
+    /// 4 KiB physical sectors, e.g. of 512e disks.
+    pub const PHYSICAL_4K: Self = Self(4096);
+
+    /// Align to `bytes`, e.g. the physical block size of a disk or the erase block size
+    /// of an SD card or eMMC.
+    pub const fn new(bytes: u64) -> Self {
+        Self(bytes)
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.0
+    }
+
+    /// The alignment in logical blocks, at least one block.
+    ///
+    /// Aligned LBAs start at a byte offset that is a multiple of the alignment, so an alignment
+    /// that is not a multiple of the block size takes more blocks than it has bytes, e.g. 3000
+    /// bytes with 512-byte blocks are 375 blocks, or 192000 bytes.
+    pub fn blocks(&self, lbs: LogicalBlockSize) -> u64 {
+        (self.0 / gcd(self.0, lbs as u64)).max(1)
+    }
+
+    /// Round `lba` up to the alignment, `None` on overflow.
+    pub fn align_up(&self, lba: u64, lbs: LogicalBlockSize) -> Option<u64> {
+        let blocks = self.blocks(lbs);
+        lba.checked_next_multiple_of(blocks)
+    }
+
+    /// Round `lba` down to the alignment.
+    pub fn align_down(&self, lba: u64, lbs: LogicalBlockSize) -> u64 {
+        lba - lba % self.blocks(lbs)
+    }
+
+    pub fn is_aligned(&self, lba: u64, lbs: LogicalBlockSize) -> bool {
+        lba.is_multiple_of(self.blocks(lbs))
+    }
+}
+
+impl Default for Alignment {
+    fn default() -> Self {
+        Self::MIB
+    }
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn blocks() {
+        assert_eq!(Alignment::MIB.blocks(LogicalBlockSize::Lb512), 2048);
+        assert_eq!(Alignment::MIB.blocks(LogicalBlockSize::Lb4096), 256);
+        assert_eq!(Alignment::PHYSICAL_4K.blocks(LogicalBlockSize::Lb512), 8);
+        assert_eq!(Alignment::PHYSICAL_4K.blocks(LogicalBlockSize::Lb4096), 1);
+        assert_eq!(Alignment::new(512).blocks(LogicalBlockSize::Lb4096), 1);
+        assert_eq!(Alignment::new(3000).blocks(LogicalBlockSize::Lb512), 375);
+        assert_eq!(Alignment::new(0).blocks(LogicalBlockSize::Lb512), 1);
+    }
+
+    #[test]
+    fn align() {
+        let lbs = LogicalBlockSize::Lb512;
+        let alignment = Alignment::PHYSICAL_4K;
+        assert_eq!(alignment.align_up(0, lbs), Some(0));
+        assert_eq!(alignment.align_up(34, lbs), Some(40));
+        assert_eq!(alignment.align_up(40, lbs), Some(40));
+        assert_eq!(alignment.align_up(u64::MAX, lbs), None);
+        assert_eq!(alignment.align_down(47, lbs), 40);
+        assert_eq!(alignment.align_down(40, lbs), 40);
+        assert!(alignment.is_aligned(2048, lbs));
+        assert!(!alignment.is_aligned(2049, lbs));
+
+        let alignment = Alignment::new(3000);
+        assert_eq!(alignment.align_up(34, lbs), Some(375));
+        assert!(!alignment.is_aligned(6, lbs));
+    }
+}
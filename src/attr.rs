@@ -0,0 +1,282 @@
+use crate::{ErrorKind, GptError, PartitionType};
+
+const REQUIRED_PARTITION: u64 = 1 << 0;
+const NO_BLOCK_IO_PROTOCOL: u64 = 1 << 1;
+const LEGACY_BIOS_BOOTABLE: u64 = 1 << 2;
+const TYPE_SPECIFIC_SHIFT: u32 = 48;
+
+/// Partition types of the Discoverable Partitions Specification that carry data.
+const DISCOVERABLE_DATA: &[PartitionType] = &[
+    PartitionType::LINUX_ROOT_X86,
+    PartitionType::LINUX_ROOT_X86_64,
+    PartitionType::LINUX_ROOT_ARM,
+    PartitionType::LINUX_ROOT_ARM64,
+    PartitionType::LINUX_ROOT_RISCV64,
+    PartitionType::LINUX_USR_X86_64,
+    PartitionType::LINUX_USR_ARM64,
+    PartitionType::LINUX_HOME,
+    PartitionType::LINUX_SRV,
+    PartitionType::LINUX_VAR,
+    PartitionType::LINUX_VAR_TMP,
+];
+
+/// The attributes of a GUID Partition Entry.
+///
+/// Bits 0-2 are defined by UEFI for all partitions, bits 48-63 are defined by the partition type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PartAttributes(u64);
+
+impl PartAttributes {
+    pub const fn from_bits(bits: u64) -> Self {
+        Self(bits)
+    }
+
+    pub const fn bits(&self) -> u64 {
+        self.0
+    }
+
+    /// The partition is required for the platform to function and must not be deleted.
+    pub fn is_required(&self) -> bool {
+        self.0 & REQUIRED_PARTITION != 0
+    }
+
+    pub fn set_required(&mut self, value: bool) {
+        self.set_bits(REQUIRED_PARTITION, value);
+    }
+
+    /// Firmware must not produce an EFI_BLOCK_IO_PROTOCOL device for the partition.
+    pub fn is_no_block_io_protocol(&self) -> bool {
+        self.0 & NO_BLOCK_IO_PROTOCOL != 0
+    }
+
+    pub fn set_no_block_io_protocol(&mut self, value: bool) {
+        self.set_bits(NO_BLOCK_IO_PROTOCOL, value);
+    }
+
+    /// The partition may be bootable by legacy BIOS firmware.
+    pub fn is_legacy_bios_bootable(&self) -> bool {
+        self.0 & LEGACY_BIOS_BOOTABLE != 0
+    }
+
+    pub fn set_legacy_bios_bootable(&mut self, value: bool) {
+        self.set_bits(LEGACY_BIOS_BOOTABLE, value);
+    }
+
+    /// The raw type-specific bits 48-63.
+    pub fn type_specific(&self) -> u16 {
+        (self.0 >> TYPE_SPECIFIC_SHIFT) as u16
+    }
+
+    pub fn set_type_specific(&mut self, bits: u16) {
+        self.0 = self.0 & !(0xFFFF << TYPE_SPECIFIC_SHIFT) | (bits as u64) << TYPE_SPECIFIC_SHIFT;
+    }
+
+    /// Whether `attr` applies to partitions of type `kind` and is set.
+    pub fn has_type_attribute(&self, kind: PartitionType, attr: TypeAttribute) -> bool {
+        attr.applies_to(kind) && self.0 & attr.mask() != 0
+    }
+
+    /// Set or clear `attr`, fails if it does not apply to partitions of type `kind`.
+    pub fn set_type_attribute(
+        &mut self,
+        kind: PartitionType,
+        attr: TypeAttribute,
+        value: bool,
+    ) -> Result<(), GptError> {
+        if !attr.applies_to(kind) {
+            return Err(GptError::new(ErrorKind::PartAttribute));
+        }
+        self.set_bits(attr.mask(), value);
+        Ok(())
+    }
+
+    /// The type-specific attributes that are set, decoded for partitions of type `kind`.
+    pub fn type_attributes(&self, kind: PartitionType) -> impl Iterator<Item = TypeAttribute> {
+        let attrs = *self;
+        TypeAttribute::ALL
+            .iter()
+            .copied()
+            .filter(move |attr| attrs.has_type_attribute(kind, *attr))
+    }
+
+    fn set_bits(&mut self, mask: u64, value: bool) {
+        if value {
+            self.0 |= mask;
+        } else {
+            self.0 &= !mask;
+        }
+    }
+}
+
+impl From<u64> for PartAttributes {
+    fn from(value: u64) -> Self {
+        Self(value)
+    }
+}
+
+impl From<PartAttributes> for u64 {
+    fn from(value: PartAttributes) -> Self {
+        value.0
+    }
+}
+
+/// An attribute in the type-specific bits 48-63, whose meaning depends on the partition type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypeAttribute {
+    /// Microsoft basic data: the volume is read-only.
+    BasicDataReadOnly,
+    /// Microsoft basic data: the volume is a shadow copy of another volume.
+    BasicDataShadowCopy,
+    /// Microsoft basic data: the volume is hidden.
+    BasicDataHidden,
+    /// Microsoft basic data: the volume is not assigned a drive letter.
+    BasicDataNoDriveLetter,
+    /// Discoverable partitions: the partition is not mounted or used automatically.
+    NoAuto,
+    /// Discoverable partitions: the partition is mounted read-only.
+    ReadOnly,
+    /// Discoverable partitions: the file system is grown to the partition size on mount.
+    GrowFs,
+}
+
+impl TypeAttribute {
+    const ALL: [TypeAttribute; 7] = [
+        Self::BasicDataReadOnly,
+        Self::BasicDataShadowCopy,
+        Self::BasicDataHidden,
+        Self::BasicDataNoDriveLetter,
+        Self::NoAuto,
+        Self::ReadOnly,
+        Self::GrowFs,
+    ];
+
+    /// Bit number of the attribute in the attributes field.
+    pub fn bit(&self) -> u32 {
+        match self {
+            Self::BasicDataReadOnly => 60,
+            Self::BasicDataShadowCopy => 61,
+            Self::BasicDataHidden => 62,
+            Self::BasicDataNoDriveLetter => 63,
+            Self::NoAuto => 63,
+            Self::ReadOnly => 60,
+            Self::GrowFs => 59,
+        }
+    }
+
+    /// Whether the attribute is defined for partitions of type `kind`.
+    pub fn applies_to(&self, kind: PartitionType) -> bool {
+        match self {
+            Self::BasicDataReadOnly
+            | Self::BasicDataShadowCopy
+            | Self::BasicDataHidden
+            | Self::BasicDataNoDriveLetter => kind == PartitionType::MICROSOFT_BASIC_DATA,
+            Self::NoAuto => {
+                DISCOVERABLE_DATA.contains(&kind)
+                    || kind == PartitionType::LINUX_SWAP
+                    || kind == PartitionType::LINUX_EXTENDED_BOOT
+            }
+            Self::ReadOnly | Self::GrowFs => DISCOVERABLE_DATA.contains(&kind),
+        }
+    }
+
+    fn mask(&self) -> u64 {
+        1 << self.bit()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloc::vec::Vec;
+
+    /// The raw bits of the attributes with only `attr` set for partitions of type `kind`.
+    fn type_attribute_bits(kind: PartitionType, attr: TypeAttribute) -> u64 {
+        let mut attrs = PartAttributes::default();
+        attrs.set_type_attribute(kind, attr, true).unwrap();
+        attrs.bits()
+    }
+
+    #[test]
+    fn uefi_bits() {
+        let mut attrs = PartAttributes::default();
+        attrs.set_required(true);
+        attrs.set_no_block_io_protocol(true);
+        attrs.set_legacy_bios_bootable(true);
+        assert_eq!(attrs.bits(), 0b111);
+        attrs.set_no_block_io_protocol(false);
+        assert_eq!(attrs.bits(), 0b101);
+        assert!(attrs.is_required() && attrs.is_legacy_bios_bootable());
+        assert!(!attrs.is_no_block_io_protocol());
+    }
+
+    #[test]
+    fn type_attribute_bit_positions() {
+        let basic_data = PartitionType::MICROSOFT_BASIC_DATA;
+        for (attr, bit) in [
+            (TypeAttribute::BasicDataReadOnly, 60),
+            (TypeAttribute::BasicDataShadowCopy, 61),
+            (TypeAttribute::BasicDataHidden, 62),
+            (TypeAttribute::BasicDataNoDriveLetter, 63),
+        ] {
+            assert_eq!(type_attribute_bits(basic_data, attr), 1 << bit);
+        }
+        let home = PartitionType::LINUX_HOME;
+        for (attr, bit) in [
+            (TypeAttribute::NoAuto, 63),
+            (TypeAttribute::ReadOnly, 60),
+            (TypeAttribute::GrowFs, 59),
+        ] {
+            assert_eq!(type_attribute_bits(home, attr), 1 << bit);
+        }
+    }
+
+    #[test]
+    fn type_attribute_of_other_type() {
+        let mut attrs = PartAttributes::from_bits(1);
+        for (kind, attr) in [
+            (PartitionType::LINUX_HOME, TypeAttribute::BasicDataHidden),
+            (PartitionType::MICROSOFT_BASIC_DATA, TypeAttribute::NoAuto),
+            (PartitionType::LINUX_SWAP, TypeAttribute::GrowFs),
+            (PartitionType::EFI_SYSTEM, TypeAttribute::ReadOnly),
+        ] {
+            let err = attrs.set_type_attribute(kind, attr, true).err().unwrap();
+            assert_eq!(err.kind(), ErrorKind::PartAttribute);
+        }
+        assert_eq!(attrs.bits(), 1);
+        assert_eq!(
+            type_attribute_bits(PartitionType::LINUX_SWAP, TypeAttribute::NoAuto),
+            1 << 63
+        );
+    }
+
+    #[test]
+    fn type_attributes_decoded_by_type() {
+        let attrs = PartAttributes::from_bits(1 << 63 | 1 << 60);
+        assert_eq!(
+            attrs
+                .type_attributes(PartitionType::MICROSOFT_BASIC_DATA)
+                .collect::<Vec<_>>(),
+            [
+                TypeAttribute::BasicDataReadOnly,
+                TypeAttribute::BasicDataNoDriveLetter
+            ]
+        );
+        assert_eq!(
+            attrs
+                .type_attributes(PartitionType::LINUX_HOME)
+                .collect::<Vec<_>>(),
+            [TypeAttribute::NoAuto, TypeAttribute::ReadOnly]
+        );
+        assert_eq!(attrs.type_attributes(PartitionType::EFI_SYSTEM).count(), 0);
+    }
+
+    #[test]
+    fn type_specific_bits() {
+        let mut attrs = PartAttributes::from_bits(0x0000_FFFF_FFFF_FFFF);
+        attrs.set_type_specific(0x8001);
+        assert_eq!(attrs.bits(), 0x8001_FFFF_FFFF_FFFF);
+        assert_eq!(attrs.type_specific(), 0x8001);
+        attrs.set_type_specific(0);
+        assert_eq!(attrs.bits(), 0x0000_FFFF_FFFF_FFFF);
+    }
+}
@@ -0,0 +1,186 @@
+use crate::{
+    ErrorKind, GptError, GuidPartTable, Header, LogicalBlockSize, PartEntry, PartTableEntry,
+    PartUUID, ProtectiveMbr,
+    gpt::MAX_PART_ENTRY_ARRAY_SIZE,
+    hdr::{NUMBER_OF_PARTITION_ENTRIES_OFFSET, PRIMARY_HEADER_LBA},
+};
+use alloc::vec;
+
+const DEFAULT_NUM_PART_ENTRIES: u32 = 128;
+const PART_ENTRY_SIZE: u32 = 128;
+/// A minimum of 16,384 bytes of space must be reserved for the GUID Partition Entry array.
+const MIN_PART_ENTRY_ARRAY_SIZE: u64 = 16384;
+
+/// Creates a new, empty GUID Partition Table for a disk.
+pub struct GptBuilder {
+    num_blocks: u64,
+    lbs: LogicalBlockSize,
+    disk_guid: PartUUID,
+    num_part_entries: u32,
+}
+
+impl GptBuilder {
+    /// `num_blocks` is the size of the disk in logical blocks.
+    pub fn new(num_blocks: u64, lbs: LogicalBlockSize, disk_guid: PartUUID) -> Self {
+        Self {
+            num_blocks,
+            lbs,
+            disk_guid,
+            num_part_entries: DEFAULT_NUM_PART_ENTRIES,
+        }
+    }
+
+    /// Number of entries in the partition entry array, 128 by default and at most 131,072.
+    pub fn num_part_entries(mut self, num_part_entries: u32) -> Self {
+        self.num_part_entries = num_part_entries;
+        self
+    }
+
+    pub fn build(self) -> Result<GuidPartTable, GptError> {
+        let array_size = self.num_part_entries as u64 * PART_ENTRY_SIZE as u64;
+        if array_size > MAX_PART_ENTRY_ARRAY_SIZE as u64 {
+            return Err(GptError::new(ErrorKind::HdrPartEntryArraySize)
+                .at(NUMBER_OF_PARTITION_ENTRIES_OFFSET)
+                .mismatch(MAX_PART_ENTRY_ARRAY_SIZE as _, array_size));
+        }
+        let array_blocks = array_size
+            .max(MIN_PART_ENTRY_ARRAY_SIZE)
+            .div_ceil(self.lbs as u64);
+
+        let part_entry_lba = PRIMARY_HEADER_LBA + 1;
+        let first_usable_lba = part_entry_lba + array_blocks;
+        let alternate_lba = self
+            .num_blocks
+            .checked_sub(1)
+            .ok_or(GptError::new(ErrorKind::DiskTooSmall))?;
+        let backup_part_entry_lba = alternate_lba
+            .checked_sub(array_blocks)
+            .ok_or(GptError::new(ErrorKind::DiskTooSmall))?;
+        let last_usable_lba = backup_part_entry_lba
+            .checked_sub(1)
+            .filter(|last| *last >= first_usable_lba)
+            .ok_or(GptError::new(ErrorKind::DiskTooSmall))?;
+
+        let part_table = PartTableEntry {
+            entries: vec![PartEntry::default(); self.num_part_entries as usize],
+        };
+        let array = part_table.serialize_part_entries(PART_ENTRY_SIZE as _);
+
+        let mut header = Header::new(self.disk_guid);
+        header.my_lba = PRIMARY_HEADER_LBA;
+        header.alternate_lba = alternate_lba;
+        header.first_usable_lba = first_usable_lba;
+        header.last_usable_lba = last_usable_lba;
+        header.part_entry_lba = part_entry_lba;
+        header.num_part_entries = self.num_part_entries;
+        header.part_entry_size = PART_ENTRY_SIZE;
+        header.crc32_part_entry_array = crate::crc32(&array);
+        header.update_crc32();
+        let backup = header.alternate(backup_part_entry_lba);
+
+        Ok(GuidPartTable::new(
+            self.lbs,
+            ProtectiveMbr::new(self.num_blocks),
+            header,
+            backup,
+            part_table,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        Deserialize, GptCopy, Serialize,
+        fixture::{LBS, NUM_BLOCKS, table},
+    };
+
+    fn build(num_blocks: u64, lbs: LogicalBlockSize) -> Result<GuidPartTable, GptError> {
+        GptBuilder::new(num_blocks, lbs, PartUUID::from_u128(1)).build()
+    }
+
+    #[test]
+    fn layout_512() {
+        let table = table();
+        let (header, backup) = (table.header(), table.backup_header());
+        assert_eq!((header.my_lba, header.alternate_lba), (1, 16383));
+        assert_eq!(
+            (header.first_usable_lba, header.last_usable_lba),
+            (34, 16350)
+        );
+        assert_eq!((header.part_entry_lba, backup.part_entry_lba), (2, 16351));
+        assert_eq!(
+            (header.num_part_entries, header.part_entry_size),
+            (128, 128)
+        );
+        assert!(header.is_alternate_of(backup));
+        header.validate(1, NUM_BLOCKS, LBS).unwrap();
+        backup.validate(16383, NUM_BLOCKS, LBS).unwrap();
+        assert_eq!(table.copy(), GptCopy::Primary);
+        assert_eq!(table.mbr(), &ProtectiveMbr::new(NUM_BLOCKS));
+    }
+
+    #[test]
+    fn layout_4096() {
+        let table = build(4096, LogicalBlockSize::Lb4096).unwrap();
+        let header = table.header();
+        assert_eq!((header.first_usable_lba, header.last_usable_lba), (6, 4090));
+        assert_eq!(table.backup_header().part_entry_lba, 4091);
+        header.validate(1, 4096, LogicalBlockSize::Lb4096).unwrap();
+    }
+
+    #[test]
+    fn larger_entry_array() {
+        let table = GptBuilder::new(NUM_BLOCKS, LBS, PartUUID::from_u128(1))
+            .num_part_entries(256)
+            .build()
+            .unwrap();
+        let header = table.header();
+        assert_eq!(
+            (header.first_usable_lba, header.last_usable_lba),
+            (66, 16318)
+        );
+        assert_eq!(table.part_table().entries.len(), 256);
+    }
+
+    #[test]
+    fn headers_and_crc32_round_trip() {
+        let table = table();
+        for header in [table.header(), table.backup_header()] {
+            let bytes = header.serialize(LBS as _);
+            assert_eq!(&Header::deserialize(&bytes).unwrap(), header);
+        }
+        let array = table.part_table().serialize_part_entries(128);
+        assert_eq!(crate::crc32(&array), table.header().crc32_part_entry_array);
+    }
+
+    #[test]
+    fn disk_too_small() {
+        for num_blocks in [0, 1, 34, 67] {
+            let err = build(num_blocks, LBS).err().unwrap();
+            assert_eq!(err.kind(), ErrorKind::DiskTooSmall, "{}", num_blocks);
+        }
+        let table = build(68, LBS).unwrap();
+        assert_eq!(
+            table.header().first_usable_lba,
+            table.header().last_usable_lba
+        );
+    }
+
+    #[test]
+    fn too_many_entries() {
+        let build = |num_part_entries| {
+            GptBuilder::new(131072, LBS, PartUUID::from_u128(1))
+                .num_part_entries(num_part_entries)
+                .build()
+        };
+        let table = build(131_072).unwrap();
+        assert_eq!(table.header().first_usable_lba, 32770);
+        for num_part_entries in [131_073, 140_000, u32::MAX] {
+            let err = build(num_part_entries).err().unwrap();
+            assert_eq!(err.kind(), ErrorKind::HdrPartEntryArraySize);
+            assert_eq!(err.found(), Some(num_part_entries as u64 * 128));
+        }
+    }
+}
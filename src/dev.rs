@@ -0,0 +1,245 @@
+use crate::{ErrorKind, GptError, LogicalBlockSize};
+
+/// A random access storage that is addressed in logical blocks.
+pub trait BlockDevice {
+    /// Logical block size of the device.
+    fn block_size(&self) -> LogicalBlockSize;
+
+    /// Total number of logical blocks of the device.
+    fn num_blocks(&self) -> u64;
+
+    /// Read `buf.len() / block_size` blocks starting at `lba` into `buf`.
+    /// The length of `buf` must be a multiple of the logical block size.
+    fn read_blocks(&self, lba: u64, buf: &mut [u8]) -> Result<(), GptError>;
+
+    /// Write `buf.len() / block_size` blocks starting at `lba` from `buf`.
+    /// The length of `buf` must be a multiple of the logical block size.
+    fn write_blocks(&mut self, lba: u64, buf: &[u8]) -> Result<(), GptError>;
+}
+
+/// Check that `len` bytes starting at `lba` are a whole number of blocks inside `dev`.
+fn check_block_range<D: BlockDevice + ?Sized>(
+    dev: &D,
+    lba: u64,
+    len: usize,
+) -> Result<(), GptError> {
+    let lbs = dev.block_size() as usize;
+    if !len.is_multiple_of(lbs) {
+        return Err(GptError::new(ErrorKind::DevBufferSize));
+    }
+    match lba.checked_add((len / lbs) as u64) {
+        Some(end) if end <= dev.num_blocks() => Ok(()),
+        _ => Err(GptError::new(ErrorKind::DevOutOfRange)),
+    }
+}
+
+/// A block device backed by memory, e.g. `Vec<u8>` or `&mut [u8]`.
+pub struct MemDevice<T> {
+    data: T,
+    lbs: LogicalBlockSize,
+}
+
+impl<T: AsRef<[u8]>> MemDevice<T> {
+    /// The length of `data` must be a multiple of the logical block size.
+    pub fn new(data: T, lbs: LogicalBlockSize) -> Result<Self, GptError> {
+        if !data.as_ref().len().is_multiple_of(lbs as usize) {
+            return Err(GptError::new(ErrorKind::DevBufferSize));
+        }
+        Ok(Self { data, lbs })
+    }
+
+    pub fn into_inner(self) -> T {
+        self.data
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> BlockDevice for MemDevice<T> {
+    fn block_size(&self) -> LogicalBlockSize {
+        self.lbs
+    }
+
+    fn num_blocks(&self) -> u64 {
+        (self.data.as_ref().len() / self.lbs as usize) as u64
+    }
+
+    fn read_blocks(&self, lba: u64, buf: &mut [u8]) -> Result<(), GptError> {
+        check_block_range(self, lba, buf.len())?;
+        let start = lba as usize * self.lbs as usize;
+        buf.copy_from_slice(&self.data.as_ref()[start..start + buf.len()]);
+        Ok(())
+    }
+
+    fn write_blocks(&mut self, lba: u64, buf: &[u8]) -> Result<(), GptError> {
+        check_block_range(self, lba, buf.len())?;
+        let start = lba as usize * self.lbs as usize;
+        self.data.as_mut()[start..start + buf.len()].copy_from_slice(buf);
+        Ok(())
+    }
+}
+
+/// A block device backed by a file, e.g. a raw disk image.
+#[cfg(feature = "std")]
+pub struct FileDevice {
+    file: std::fs::File,
+    lbs: LogicalBlockSize,
+    num_blocks: u64,
+}
+
+#[cfg(feature = "std")]
+impl FileDevice {
+    /// The size of `file` is rounded down to a whole number of blocks.
+    pub fn new(file: std::fs::File, lbs: LogicalBlockSize) -> Result<Self, GptError> {
+        // The metadata of a block device has a length of 0, seeking to its end gives its size.
+        let len = std::io::Seek::seek(&mut &file, std::io::SeekFrom::End(0))?;
+        Ok(Self {
+            file,
+            lbs,
+            num_blocks: len / lbs as u64,
+        })
+    }
+
+    /// Open the image at `path` for reading and writing.
+    pub fn open<P: AsRef<std::path::Path>>(
+        path: P,
+        lbs: LogicalBlockSize,
+    ) -> Result<Self, GptError> {
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)?;
+        Self::new(file, lbs)
+    }
+
+    pub fn into_inner(self) -> std::fs::File {
+        self.file
+    }
+
+    #[cfg(unix)]
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> std::io::Result<()> {
+        std::os::unix::fs::FileExt::read_exact_at(&self.file, buf, offset)
+    }
+
+    #[cfg(unix)]
+    fn write_at(&self, buf: &[u8], offset: u64) -> std::io::Result<()> {
+        std::os::unix::fs::FileExt::write_all_at(&self.file, buf, offset)
+    }
+
+    #[cfg(windows)]
+    fn read_at(&self, mut buf: &mut [u8], mut offset: u64) -> std::io::Result<()> {
+        use std::os::windows::fs::FileExt;
+        while !buf.is_empty() {
+            match self.file.seek_read(buf, offset)? {
+                0 => return Err(std::io::ErrorKind::UnexpectedEof.into()),
+                n => {
+                    buf = &mut buf[n..];
+                    offset += n as u64;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(windows)]
+    fn write_at(&self, mut buf: &[u8], mut offset: u64) -> std::io::Result<()> {
+        use std::os::windows::fs::FileExt;
+        while !buf.is_empty() {
+            match self.file.seek_write(buf, offset)? {
+                0 => return Err(std::io::ErrorKind::WriteZero.into()),
+                n => {
+                    buf = &buf[n..];
+                    offset += n as u64;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl BlockDevice for FileDevice {
+    fn block_size(&self) -> LogicalBlockSize {
+        self.lbs
+    }
+
+    fn num_blocks(&self) -> u64 {
+        self.num_blocks
+    }
+
+    fn read_blocks(&self, lba: u64, buf: &mut [u8]) -> Result<(), GptError> {
+        check_block_range(self, lba, buf.len())?;
+        Ok(self.read_at(buf, lba * self.lbs as u64)?)
+    }
+
+    fn write_blocks(&mut self, lba: u64, buf: &[u8]) -> Result<(), GptError> {
+        check_block_range(self, lba, buf.len())?;
+        Ok(self.write_at(buf, lba * self.lbs as u64)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixture::{LBS, mem_device};
+    use alloc::vec;
+
+    #[test]
+    fn mem_device_bounds() {
+        let mut dev = mem_device(4);
+        assert_eq!(dev.num_blocks(), 4);
+        let mut buf = vec![0xA5; 2 * LBS as usize];
+        dev.write_blocks(2, &buf).unwrap();
+        for (lba, len) in [(3, 2), (4, 1), (u64::MAX, 1)] {
+            let err = dev
+                .read_blocks(lba, &mut buf[..len * LBS as usize])
+                .err()
+                .unwrap();
+            assert_eq!(err.kind(), ErrorKind::DevOutOfRange);
+            let err = dev
+                .write_blocks(lba, &buf[..len * LBS as usize])
+                .err()
+                .unwrap();
+            assert_eq!(err.kind(), ErrorKind::DevOutOfRange);
+        }
+        dev.read_blocks(4, &mut []).unwrap();
+        assert_eq!(dev.into_inner()[..2 * LBS as usize], [0; 1024]);
+    }
+
+    #[test]
+    fn buffer_size() {
+        let err = MemDevice::new(vec![0; 1000], LBS).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::DevBufferSize);
+        let mut dev = mem_device(4);
+        let err = dev.read_blocks(0, &mut [0; 100]).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::DevBufferSize);
+        let err = dev.write_blocks(0, &[0; 513]).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::DevBufferSize);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn file_device_round_trip() {
+        let path =
+            std::env::temp_dir().join(std::format!("gpt-file-device-{}.img", std::process::id()));
+        std::fs::write(&path, vec![0; 4 * LBS as usize + 100]).unwrap();
+        let mut dev = FileDevice::open(&path, LBS).unwrap();
+        assert_eq!(dev.num_blocks(), 4);
+        let data = (0..2 * LBS as usize)
+            .map(|i| i as u8)
+            .collect::<std::vec::Vec<_>>();
+        dev.write_blocks(2, &data).unwrap();
+        let err = dev.write_blocks(3, &data).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::DevOutOfRange);
+        drop(dev);
+
+        let dev = FileDevice::open(&path, LBS).unwrap();
+        let mut buf = vec![0; 2 * LBS as usize];
+        dev.read_blocks(2, &mut buf).unwrap();
+        assert_eq!(buf, data);
+        std::fs::remove_file(&path).unwrap();
+
+        let err = FileDevice::open(&path, LBS).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::DevIo);
+        assert_eq!(err.io_error().unwrap().kind(), std::io::ErrorKind::NotFound);
+        assert!(err.io_error().unwrap().raw_os_error().is_some());
+    }
+}
@@ -1,37 +1,163 @@
-use crate::{Deserialize, LittleEndianBytes, PartUUID, Serialize, UUID_SIZE};
-use alloc::vec::Vec;
-use core::ops::Deref;
+use crate::{
+    Deserialize, ErrorKind, GptError, LittleEndianBytes, PartAttributes, PartUUID, PartitionType,
+    Serialize, Structure, UUID_SIZE, check_buf, read_u64,
+};
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{
+    fmt::{Display, Write},
+    ops::{Deref, RangeInclusive},
+    str::FromStr,
+};
 
 pub struct PartTableEntry {
     pub entries: Vec<PartEntry>,
 }
 
 impl PartTableEntry {
+    /// Parse the partition entry array in `data` and verify it against `crc32`.
+    ///
+    /// Every used entry must lie inside `usable`, with its starting LBA not after its ending LBA,
+    /// and must not overlap any other used entry.
     pub(crate) fn generate_part_entries(
         data: &[u8],
         part_entry_size: usize,
-    ) -> Result<Self, crate::GptError> {
+        crc32: u32,
+        usable: RangeInclusive<u64>,
+    ) -> Result<Self, GptError> {
+        if part_entry_size < PartEntry::SIZE {
+            return Err(GptError::new(ErrorKind::HdrPartEntrySize)
+                .mismatch(PartEntry::SIZE as _, part_entry_size as _));
+        }
+        Self::check_crc32(data, crc32)?;
         let part_entry_num = data.len() / part_entry_size;
         let mut entries = Vec::with_capacity(part_entry_num);
         for index in 0..part_entry_num {
             let start = index * part_entry_size;
-            entries.push(PartEntry::deserialize(
-                &data[start..start + part_entry_size],
-            )?);
+            entries.push(
+                PartEntry::deserialize(&data[start..start + part_entry_size])
+                    .map_err(|err| err.inside(Structure::PartEntry(index as _), start))?,
+            );
+        }
+        let table = Self { entries };
+        table.check_ranges(usable, part_entry_size)?;
+        Ok(table)
+    }
+
+    /// Check the ranges of the used entries of an array of `part_entry_size` byte slots.
+    fn check_ranges(
+        &self,
+        usable: RangeInclusive<u64>,
+        part_entry_size: usize,
+    ) -> Result<(), GptError> {
+        let mut used = Vec::new();
+        for (index, entry) in self.entries.iter().enumerate() {
+            if !entry.is_used() {
+                continue;
+            }
+            check_range(
+                index,
+                entry.starting_lba,
+                entry.ending_lba,
+                &usable,
+                part_entry_size,
+            )?;
+            used.push(index);
+        }
+        // Sorted by starting LBA, an entry overlaps another one if it starts before the end of
+        // the one just before it.
+        used.sort_unstable_by_key(|index| (self.entries[*index].starting_lba, *index));
+        for pair in used.windows(2) {
+            let (prev, next) = (&self.entries[pair[0]], &self.entries[pair[1]]);
+            if next.starting_lba <= prev.ending_lba {
+                return Err(overlap(
+                    pair[1],
+                    prev.ending_lba,
+                    next.starting_lba,
+                    part_entry_size,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_crc32(bytes: &[u8], crc32: u32) -> Result<(), GptError> {
+        let checksum = crate::crc32(bytes);
+        if crc32 == checksum {
+            Ok(())
+        } else {
+            Err(GptError::new(ErrorKind::PartEntryArrayCrc32).mismatch(checksum as _, crc32 as _))
         }
-        Ok(Self { entries })
     }
 
     pub(crate) fn serialize_part_entries(&self, part_entry_size: usize) -> Vec<u8> {
-        let size = self.entries.len() * part_entry_size;
-        let mut bytes = vec![0; size];
-        self.entries.iter().enumerate().for_each(|(index, record)| {
-            let start = index * part_entry_size;
-            bytes[start..start + part_entry_size]
-                .copy_from_slice(&record.serialize(part_entry_size));
-        });
+        let mut bytes = vec![0; self.entries.len() * part_entry_size];
+        self.serialize_part_entries_into(&mut bytes, part_entry_size)
+            .expect("buffer holds the whole entry array");
         bytes
     }
+
+    /// Write the entries as an array of `part_entry_size` byte slots at the start of `buf`.
+    ///
+    /// Fails if `buf` is shorter than the array or `part_entry_size` is below 128 bytes.
+    pub fn serialize_part_entries_into(
+        &self,
+        buf: &mut [u8],
+        part_entry_size: usize,
+    ) -> Result<(), GptError> {
+        if part_entry_size < PartEntry::SIZE {
+            return Err(GptError::new(ErrorKind::HdrPartEntrySize)
+                .mismatch(PartEntry::SIZE as _, part_entry_size as _));
+        }
+        let size = self.entries.len().saturating_mul(part_entry_size);
+        if buf.len() < size {
+            return Err(GptError::new(ErrorKind::Truncated).mismatch(size as _, buf.len() as _));
+        }
+        for (index, (entry, slot)) in self
+            .entries
+            .iter()
+            .zip(buf.chunks_exact_mut(part_entry_size))
+            .enumerate()
+        {
+            entry.serialize_into(slot).map_err(|err| {
+                err.inside(Structure::PartEntry(index as _), index * part_entry_size)
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Check that the entry at `index` is not inverted and lies inside `usable`.
+fn check_range(
+    index: usize,
+    starting_lba: u64,
+    ending_lba: u64,
+    usable: &RangeInclusive<u64>,
+    part_entry_size: usize,
+) -> Result<(), GptError> {
+    if starting_lba > ending_lba || !usable.contains(&starting_lba) || !usable.contains(&ending_lba)
+    {
+        return Err(GptError::new(ErrorKind::PartRange)
+            .at(STARTING_LBA_OFFSET)
+            .inside(Structure::PartEntry(index as _), index * part_entry_size));
+    }
+    Ok(())
+}
+
+/// The entry at `index` starts at `starting_lba`, before the end of the entry just before it.
+fn overlap(
+    index: usize,
+    prev_ending_lba: u64,
+    starting_lba: u64,
+    part_entry_size: usize,
+) -> GptError {
+    GptError::new(ErrorKind::PartOverlap)
+        .at(STARTING_LBA_OFFSET)
+        .mismatch(prev_ending_lba + 1, starting_lba)
+        .inside(Structure::PartEntry(index as _), index * part_entry_size)
 }
 
 const PART_TYPE_GUID_OFFSET: usize = 0;
@@ -50,7 +176,12 @@ const ATTRIBUTES_OFFSET: usize = 48;
 const ATTRIBUTES_SIZE: usize = 8;
 
 const PART_NAME_OFFSET: usize = 56;
-const PART_NAME_SIZE: usize = 16;
+const PART_NAME_SIZE: usize = 72;
+/// The partition name is at most 36 UTF-16LE code units.
+const PART_NAME_UNITS: usize = PART_NAME_SIZE / 2;
+
+/// Size of the fields of a partition entry, the smallest valid `part_entry_size`.
+const PART_ENTRY_MIN_SIZE: usize = PART_NAME_OFFSET + PART_NAME_SIZE;
 
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct PartEntry {
@@ -63,15 +194,42 @@ pub struct PartEntry {
     /// Last LBA of the partition.
     pub ending_lba: u64,
     /// Partition flags.
-    pub attributes: u64,
+    pub attributes: PartAttributes,
     /// Partition name.
     pub name: PartName,
     // pub reserved size = 128 - part_entry_size
 }
 
+impl PartEntry {
+    /// An entry whose partition type GUID is zero is unused.
+    pub fn is_used(&self) -> bool {
+        self.part_type_guid.iter().any(|byte| *byte != 0)
+    }
+
+    /// The well-known partition type of the entry, if any.
+    pub fn kind(&self) -> Option<PartitionType> {
+        PartitionType::from_guid(&self.part_type_guid)
+    }
+
+    /// Number of logical blocks of the partition, zero if the ending LBA is before the starting
+    /// LBA.
+    pub fn num_blocks(&self) -> u64 {
+        self.ending_lba
+            .checked_sub(self.starting_lba)
+            .map_or(0, |blocks| blocks.saturating_add(1))
+    }
+
+    /// Whether the partition shares any logical block with `starting_lba..=ending_lba`.
+    pub fn overlaps(&self, starting_lba: u64, ending_lba: u64) -> bool {
+        self.starting_lba <= ending_lba && starting_lba <= self.ending_lba
+    }
+}
+
 impl Serialize for PartEntry {
-    fn serialize(&self, size: usize) -> Vec<u8> {
-        let mut bytes = vec![0; size];
+    const SIZE: usize = PART_ENTRY_MIN_SIZE;
+
+    fn serialize_into(&self, bytes: &mut [u8]) -> Result<(), GptError> {
+        check_buf(bytes, Self::SIZE)?;
         bytes[PART_TYPE_GUID_OFFSET..PART_TYPE_GUID_OFFSET + PART_TYPE_GUID_SIZE]
             .copy_from_slice(&self.part_type_guid);
         bytes[PART_GUID_OFFSET..PART_GUID_OFFSET + PART_GUID_SIZE].copy_from_slice(&self.part_guid);
@@ -80,9 +238,9 @@ impl Serialize for PartEntry {
         bytes[ENDING_LBA_OFFSET..ENDING_LBA_OFFSET + ENDING_LBA_SIZE]
             .copy_from_slice(&self.ending_lba.to_le_bytes());
         bytes[ATTRIBUTES_OFFSET..ATTRIBUTES_OFFSET + ATTRIBUTES_SIZE]
-            .copy_from_slice(&self.attributes.to_le_bytes());
+            .copy_from_slice(&self.attributes.bits().to_le_bytes());
         bytes[PART_NAME_OFFSET..PART_NAME_OFFSET + PART_NAME_SIZE].copy_from_slice(&self.name);
-        bytes
+        Ok(())
     }
 }
 
@@ -91,27 +249,217 @@ impl Deserialize for PartEntry {
         let mut ltbs = LittleEndianBytes::from(data);
 
         let part_type_guid =
-            PartUUID::try_from(&ltbs[PART_TYPE_GUID_OFFSET..PART_TYPE_GUID_OFFSET + UUID_SIZE])?;
-        ltbs.skip(UUID_SIZE);
-        let part_guid = PartUUID::try_from(&ltbs[PART_GUID_OFFSET..PART_GUID_OFFSET + UUID_SIZE])?;
-        ltbs.skip(UUID_SIZE);
-        let start_lba = ltbs.parse_u64().unwrap();
-        let end_lba = ltbs.parse_u64().unwrap();
-        let attrs = ltbs.parse_u64().unwrap();
-        let name = PartName(ltbs.copy_from::<PART_NAME_SIZE>(PART_NAME_OFFSET));
+            PartUUID::try_from(&ltbs.copy_from::<UUID_SIZE>(PART_TYPE_GUID_OFFSET)?[..])?;
+        let part_guid = PartUUID::try_from(&ltbs.copy_from::<UUID_SIZE>(PART_GUID_OFFSET)?[..])?;
+        let start_lba = ltbs.parse_u64()?;
+        let end_lba = ltbs.parse_u64()?;
+        let attrs = ltbs.parse_u64()?;
+        let name = PartName(ltbs.copy_from::<PART_NAME_SIZE>(PART_NAME_OFFSET)?);
         Ok(Self {
             part_type_guid,
             part_guid,
             starting_lba: start_lba,
             ending_lba: end_lba,
-            attributes: attrs,
+            attributes: PartAttributes::from_bits(attrs),
             name,
         })
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Default)]
-pub struct PartName([u8; 16]);
+/// A partition entry borrowed from a buffer, whose fields are read without allocation.
+#[derive(Debug, Clone, Copy)]
+pub struct EntryView<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> EntryView<'a> {
+    /// `data` must hold at least the 128 bytes of the entry fields.
+    pub fn new(data: &'a [u8]) -> Result<Self, GptError> {
+        if data.len() < PART_ENTRY_MIN_SIZE {
+            return Err(GptError::new(ErrorKind::Truncated)
+                .mismatch(PART_ENTRY_MIN_SIZE as _, data.len() as _));
+        }
+        Ok(Self { data })
+    }
+
+    pub fn part_type_guid(&self) -> PartUUID {
+        self.guid(PART_TYPE_GUID_OFFSET)
+    }
+
+    pub fn part_guid(&self) -> PartUUID {
+        self.guid(PART_GUID_OFFSET)
+    }
+
+    pub fn starting_lba(&self) -> u64 {
+        read_u64(self.data, STARTING_LBA_OFFSET)
+    }
+
+    pub fn ending_lba(&self) -> u64 {
+        read_u64(self.data, ENDING_LBA_OFFSET)
+    }
+
+    pub fn attributes(&self) -> PartAttributes {
+        PartAttributes::from_bits(read_u64(self.data, ATTRIBUTES_OFFSET))
+    }
+
+    pub fn name(&self) -> PartName {
+        let mut name = PartName::default();
+        name.0
+            .copy_from_slice(&self.data[PART_NAME_OFFSET..PART_NAME_OFFSET + PART_NAME_SIZE]);
+        name
+    }
+
+    /// An entry whose partition type GUID is zero is unused.
+    pub fn is_used(&self) -> bool {
+        self.data[PART_TYPE_GUID_OFFSET..PART_TYPE_GUID_OFFSET + PART_TYPE_GUID_SIZE]
+            .iter()
+            .any(|byte| *byte != 0)
+    }
+
+    /// The well-known partition type of the entry, if any.
+    pub fn kind(&self) -> Option<PartitionType> {
+        PartitionType::from_guid(&self.part_type_guid())
+    }
+
+    /// Copy the fields into an owned [`PartEntry`].
+    pub fn to_entry(&self) -> PartEntry {
+        PartEntry {
+            part_type_guid: self.part_type_guid(),
+            part_guid: self.part_guid(),
+            starting_lba: self.starting_lba(),
+            ending_lba: self.ending_lba(),
+            attributes: self.attributes(),
+            name: self.name(),
+        }
+    }
+
+    fn guid(&self, offset: usize) -> PartUUID {
+        let mut guid = PartUUID::default();
+        guid.copy_from_slice(&self.data[offset..offset + UUID_SIZE]);
+        guid
+    }
+}
+
+/// Iterator over the entries of a borrowed partition entry array, see [`HeaderView::entries`].
+///
+/// [`HeaderView::entries`]: crate::HeaderView::entries
+#[derive(Debug, Clone)]
+pub struct EntryIter<'a> {
+    chunks: core::slice::ChunksExact<'a, u8>,
+}
+
+impl<'a> EntryIter<'a> {
+    /// Iterate over `num_entries` entries of `part_entry_size` bytes at the start of `data`
+    /// after verifying them against `crc32` and checking their ranges against `usable`, like
+    /// [`PartTableEntry::generate_part_entries`].
+    pub(crate) fn new(
+        data: &'a [u8],
+        num_entries: usize,
+        part_entry_size: usize,
+        crc32: u32,
+        usable: RangeInclusive<u64>,
+    ) -> Result<Self, GptError> {
+        let size = num_entries.saturating_mul(part_entry_size);
+        if data.len() < size {
+            return Err(GptError::new(ErrorKind::Truncated).mismatch(size as _, data.len() as _));
+        }
+        PartTableEntry::check_crc32(&data[..size], crc32)?;
+        let iter = Self {
+            chunks: data[..size].chunks_exact(part_entry_size),
+        };
+        iter.check_ranges(usable, part_entry_size)?;
+        Ok(iter)
+    }
+
+    /// Check the ranges like [`PartTableEntry::check_ranges`] and report the same entry, without
+    /// allocating. Finding the entry just before each entry takes time quadratic in the number of
+    /// used entries.
+    fn check_ranges(
+        &self,
+        usable: RangeInclusive<u64>,
+        part_entry_size: usize,
+    ) -> Result<(), GptError> {
+        let used = || {
+            self.clone()
+                .enumerate()
+                .filter(|(_, entry)| entry.is_used())
+        };
+        for (index, entry) in used() {
+            check_range(
+                index,
+                entry.starting_lba(),
+                entry.ending_lba(),
+                &usable,
+                part_entry_size,
+            )?;
+        }
+        // The order of the sorted entries of the owned table.
+        let key = |(index, entry): &(usize, EntryView)| (entry.starting_lba(), *index);
+        let mut first = None;
+        for next in used() {
+            let Some(prev) = used()
+                .filter(|other| key(other) < key(&next))
+                .max_by_key(key)
+            else {
+                continue;
+            };
+            if next.1.starting_lba() <= prev.1.ending_lba()
+                && first
+                    .as_ref()
+                    .is_none_or(|(first, _)| key(&next) < key(first))
+            {
+                first = Some((next, prev));
+            }
+        }
+        match first {
+            Some(((index, next), (_, prev))) => Err(overlap(
+                index,
+                prev.ending_lba(),
+                next.starting_lba(),
+                part_entry_size,
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'a> Iterator for EntryIter<'a> {
+    type Item = EntryView<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.chunks.next().map(|data| EntryView { data })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.chunks.size_hint()
+    }
+}
+
+impl ExactSizeIterator for EntryIter<'_> {}
+
+/// A partition name, a NUL-terminated UTF-16LE string of up to 36 code units.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartName([u8; PART_NAME_SIZE]);
+
+impl PartName {
+    /// UTF-16 code units of the name, up to the first NUL.
+    pub fn units(&self) -> impl Iterator<Item = u16> + '_ {
+        self.0
+            .chunks_exact(2)
+            .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+            .take_while(|unit| *unit != 0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.units().next().is_none()
+    }
+}
+
+impl Default for PartName {
+    fn default() -> Self {
+        Self([0; PART_NAME_SIZE])
+    }
+}
 
 impl Deref for PartName {
     type Target = [u8];
@@ -120,3 +468,245 @@ impl Deref for PartName {
         &self.0
     }
 }
+
+impl TryFrom<&str> for PartName {
+    type Error = GptError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        if value.contains('\0') {
+            return Err(GptError::new(ErrorKind::PartNameNul));
+        }
+        if value.encode_utf16().count() > PART_NAME_UNITS {
+            return Err(GptError::new(ErrorKind::PartNameTooLong));
+        }
+        let mut name = Self::default();
+        for (bytes, unit) in name.0.chunks_exact_mut(2).zip(value.encode_utf16()) {
+            bytes.copy_from_slice(&unit.to_le_bytes());
+        }
+        Ok(name)
+    }
+}
+
+impl FromStr for PartName {
+    type Err = GptError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from(s)
+    }
+}
+
+impl Display for PartName {
+    /// Unpaired surrogates are shown as U+FFFD.
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        char::decode_utf16(self.units())
+            .map(|ch| ch.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .try_for_each(|ch| f.write_char(ch))
+    }
+}
+
+impl From<&PartName> for String {
+    fn from(value: &PartName) -> Self {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn crc32_mismatch_reports_stored_value_as_found() {
+        let table = PartTableEntry {
+            entries: vec![PartEntry::default(); 4],
+        };
+        let array = table.serialize_part_entries(PartEntry::SIZE);
+        let checksum = crate::crc32(&array);
+
+        let err =
+            PartTableEntry::generate_part_entries(&array, PartEntry::SIZE, !checksum, 34..=99)
+                .err()
+                .unwrap();
+        assert_eq!(err.kind(), ErrorKind::PartEntryArrayCrc32);
+        assert_eq!(err.expected(), Some(checksum as _));
+        assert_eq!(err.found(), Some(!checksum as _));
+    }
+
+    fn array(ranges: &[(u64, u64)]) -> Vec<u8> {
+        let entries = ranges
+            .iter()
+            .map(|(starting_lba, ending_lba)| PartEntry {
+                part_type_guid: PartitionType::LINUX_FILESYSTEM.guid,
+                starting_lba: *starting_lba,
+                ending_lba: *ending_lba,
+                ..Default::default()
+            })
+            .chain([PartEntry::default()])
+            .collect();
+        PartTableEntry { entries }.serialize_part_entries(PartEntry::SIZE)
+    }
+
+    fn parse(ranges: &[(u64, u64)]) -> Result<PartTableEntry, GptError> {
+        let array = array(ranges);
+        PartTableEntry::generate_part_entries(
+            &array,
+            PartEntry::SIZE,
+            crate::crc32(&array),
+            34..=99,
+        )
+    }
+
+    #[test]
+    fn entries_inside_usable_range() {
+        let table = parse(&[(60, 99), (34, 59)]).unwrap();
+        assert_eq!(table.entries.len(), 3);
+        assert_eq!(table.entries[1].num_blocks(), 26);
+    }
+
+    #[test]
+    fn inverted_entry() {
+        let err = parse(&[(34, 40), (60, 50)]).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::PartRange);
+        assert_eq!(err.structure(), Some(Structure::PartEntry(1)));
+        assert_eq!(
+            err.offset(),
+            Some((PartEntry::SIZE + STARTING_LBA_OFFSET) as _)
+        );
+    }
+
+    #[test]
+    fn entry_outside_usable_range() {
+        for range in [(33, 40), (90, 100)] {
+            let err = parse(&[range]).err().unwrap();
+            assert_eq!(err.kind(), ErrorKind::PartRange);
+        }
+    }
+
+    #[test]
+    fn overlapping_entries() {
+        let err = parse(&[(50, 60), (34, 49), (60, 70)]).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::PartOverlap);
+        assert_eq!(err.structure(), Some(Structure::PartEntry(2)));
+        assert_eq!((err.expected(), err.found()), (Some(61), Some(60)));
+    }
+
+    #[test]
+    fn num_blocks() {
+        let entry = |starting_lba, ending_lba| PartEntry {
+            starting_lba,
+            ending_lba,
+            ..Default::default()
+        };
+        assert_eq!(entry(34, 34).num_blocks(), 1);
+        assert_eq!(entry(2048, 4095).num_blocks(), 2048);
+        assert_eq!(entry(4096, 2048).num_blocks(), 0);
+        assert_eq!(entry(0, u64::MAX).num_blocks(), u64::MAX);
+    }
+
+    #[test]
+    fn crc32_round_trip() {
+        let mut array = array(&[(34, 59), (60, 99)]);
+        let crc32 = crate::crc32(&array);
+        let table =
+            PartTableEntry::generate_part_entries(&array, PartEntry::SIZE, crc32, 34..=99).unwrap();
+        assert_eq!(table.serialize_part_entries(PartEntry::SIZE), array);
+
+        // A name that changes without the CRC32 being updated.
+        array[PartEntry::SIZE + PART_NAME_OFFSET] = b'x';
+        let err = PartTableEntry::generate_part_entries(&array, PartEntry::SIZE, crc32, 34..=99)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::PartEntryArrayCrc32);
+    }
+
+    #[test]
+    fn truncated_entry() {
+        let entry = array(&[(34, 99)]);
+        for len in 0..PartEntry::SIZE {
+            assert!(PartEntry::deserialize(&entry[..len]).is_err(), "{}", len);
+            assert!(EntryView::new(&entry[..len]).is_err(), "{}", len);
+        }
+    }
+
+    #[test]
+    fn part_entry_size_below_an_entry() {
+        let array = vec![0; 1024];
+        for size in [0, 1, 127] {
+            let err =
+                PartTableEntry::generate_part_entries(&array, size, crate::crc32(&array), 34..=99)
+                    .err()
+                    .unwrap();
+            assert_eq!(err.kind(), ErrorKind::HdrPartEntrySize);
+        }
+    }
+
+    /// Check `array` through the borrowed views, with the usable range of `parse`.
+    fn views(array: &[u8], crc32: u32) -> Result<usize, GptError> {
+        let num_entries = array.len() / PartEntry::SIZE;
+        EntryIter::new(array, num_entries, PartEntry::SIZE, crc32, 34..=99).map(Iterator::count)
+    }
+
+    #[test]
+    fn views_check_like_owned_entries() {
+        for ranges in [
+            &[(34, 59), (60, 99)][..],
+            &[(34, 40), (60, 50)],
+            &[(33, 40)],
+            &[(90, 100)],
+            &[(50, 60), (34, 49), (60, 70)],
+            &[(34, 99), (40, 50), (60, 70)],
+            &[(50, 60), (50, 55), (40, 49)],
+        ] {
+            let array = array(ranges);
+            let crc32 = crate::crc32(&array);
+            assert_eq!(
+                views(&array, crc32).err(),
+                parse(ranges).err(),
+                "{:?}",
+                ranges
+            );
+            let err =
+                PartTableEntry::generate_part_entries(&array, PartEntry::SIZE, !crc32, 34..=99)
+                    .err()
+                    .unwrap();
+            assert_eq!(views(&array, !crc32).err(), Some(err));
+            assert_eq!(err.kind(), ErrorKind::PartEntryArrayCrc32);
+        }
+        let array = array(&[(34, 59), (60, 99)]);
+        assert_eq!(views(&array, crate::crc32(&array)).unwrap(), 3);
+    }
+
+    #[test]
+    fn name_length() {
+        let name = PartName::try_from("a".repeat(36).as_str()).unwrap();
+        assert_eq!(name.units().count(), 36);
+        assert_eq!(name.to_string(), "a".repeat(36));
+        let err = PartName::try_from("a".repeat(37).as_str()).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::PartNameTooLong);
+
+        // Characters outside the BMP take two code units each.
+        let crabs = "\u{1F980}".repeat(18);
+        let name = PartName::try_from(crabs.as_str()).unwrap();
+        assert_eq!(name.units().count(), 36);
+        assert_eq!(String::from(&name), crabs);
+        let err = PartName::try_from(["a", &crabs].concat().as_str())
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::PartNameTooLong);
+
+        assert!(PartName::try_from("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn name_with_nul() {
+        let err = "boot\0".parse::<PartName>().err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::PartNameNul);
+    }
+
+    #[test]
+    fn name_with_unpaired_surrogate() {
+        let mut name = PartName::default();
+        name.0[..6].copy_from_slice(&[0x3D, 0xD8, b'a', 0, 0x80, 0xDC]);
+        assert_eq!(name.units().count(), 3);
+        assert_eq!(name.to_string(), "\u{FFFD}a\u{FFFD}");
+    }
+}
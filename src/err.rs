@@ -1,8 +1,19 @@
-pub enum GptError {
+use crate::GptCopy;
+use core::fmt::{self, Display};
+
+/// The kind of a [`GptError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
     HdrSignature,
     HdrRevision,
     HdrSize,
     HdrCrc32,
+    HdrMyLba,
+    HdrAlternateLba,
+    HdrUsableRange,
+    HdrPartEntryArray,
+    HdrPartEntrySize,
+    HdrPartEntryArraySize,
     MbrUnknownNonZero,
     MbrDiskSignature,
     MbrSignature,
@@ -10,5 +21,267 @@ pub enum GptError {
     MbrPREndingChs,
     MbrPROsType,
     MbrPRStartingLba,
-    PartUUID
+    MbrPRSizeInLba,
+    MbrPRBootIndicator,
+    MbrPROverlap,
+    MbrPROutOfRange,
+    MbrNonZeroRecord,
+    MbrHybridEmpty,
+    MbrHybridTooMany,
+    MbrHybridUnmatched,
+    MbrHybridDuplicate,
+    MbrHybridType,
+    MbrEbrChain,
+    MbrGptOverlap,
+    PartUUID,
+    PartEntryArrayCrc32,
+    PartEntryCount,
+    PartIndex,
+    PartSlotUsed,
+    PartTableFull,
+    PartTypeUnused,
+    PartRange,
+    PartOverlap,
+    PartNoSpace,
+    PartAttribute,
+    PartNameTooLong,
+    PartNameNul,
+    DevIo,
+    DevBufferSize,
+    DevOutOfRange,
+    DevBlockSize,
+    DevNumBlocks,
+    DiskTooSmall,
+    Truncated,
+    Rng,
 }
+
+impl ErrorKind {
+    fn message(&self) -> &'static str {
+        match self {
+            Self::HdrSignature => "invalid GPT header signature",
+            Self::HdrRevision => "unsupported GPT header revision",
+            Self::HdrSize => "invalid GPT header size",
+            Self::HdrCrc32 => "GPT header CRC32 mismatch",
+            Self::HdrMyLba => "GPT header is not at its own LBA",
+            Self::HdrAlternateLba => "alternate LBA does not point to the other GPT header",
+            Self::HdrUsableRange => "invalid usable LBA range",
+            Self::HdrPartEntryArray => "partition entry array overlaps the disk layout",
+            Self::HdrPartEntrySize => "partition entry size is not 128 x 2^n",
+            Self::HdrPartEntryArraySize => "partition entry array is larger than 16 MiB",
+            Self::MbrUnknownNonZero => "MBR unknown field is not zero",
+            Self::MbrDiskSignature => "MBR disk signature is not zero",
+            Self::MbrSignature => "invalid MBR signature",
+            Self::MbrPRStartingChs => "invalid starting CHS of MBR partition record",
+            Self::MbrPREndingChs => "invalid ending CHS of MBR partition record",
+            Self::MbrPROsType => "invalid OS type of MBR partition record",
+            Self::MbrPRStartingLba => "invalid starting LBA of MBR partition record",
+            Self::MbrPRSizeInLba => "invalid size of MBR partition record",
+            Self::MbrPRBootIndicator => "invalid boot indicator of MBR partition record",
+            Self::MbrPROverlap => "MBR partition record overlaps another record",
+            Self::MbrPROutOfRange => "MBR partition record is outside the disk",
+            Self::MbrNonZeroRecord => "unused MBR partition record is not zero",
+            Self::MbrHybridEmpty => "a hybrid MBR mirrors at least one partition",
+            Self::MbrHybridTooMany => "a hybrid MBR mirrors at most three partitions",
+            Self::MbrHybridUnmatched => "MBR partition record matches no GPT partition",
+            Self::MbrHybridDuplicate => "GPT partition is mirrored more than once",
+            Self::MbrHybridType => "MBR partition type does not match the GPT partition type",
+            Self::MbrEbrChain => "EBR chain loops or is too long",
+            Self::MbrGptOverlap => "MBR partition overlaps the space needed by the GPT",
+            Self::PartUUID => "invalid GUID",
+            Self::PartEntryArrayCrc32 => "partition entry array CRC32 mismatch",
+            Self::PartEntryCount => "number of partition entries does not match the header",
+            Self::PartIndex => "no partition at this index",
+            Self::PartSlotUsed => "partition entry slot is already used",
+            Self::PartTableFull => "partition entry array is full",
+            Self::PartTypeUnused => "partition type GUID is zero",
+            Self::PartRange => "partition is inverted or outside the usable LBA range",
+            Self::PartOverlap => "partition overlaps another partition",
+            Self::PartNoSpace => "not enough free space for the partition",
+            Self::PartAttribute => "attribute is not defined for this partition type",
+            Self::PartNameTooLong => "partition name is longer than 36 UTF-16 code units",
+            Self::PartNameNul => "partition name contains NUL",
+            Self::DevIo => "block device I/O error",
+            Self::DevBufferSize => "buffer size is not a multiple of the block size",
+            Self::DevOutOfRange => "blocks are outside the block device",
+            Self::DevBlockSize => "block size does not match the block device",
+            Self::DevNumBlocks => "number of blocks does not match the block device",
+            Self::DiskTooSmall => "disk is too small for a GPT",
+            Self::Truncated => "buffer is too short for the structure",
+            Self::Rng => "random number generator failed",
+        }
+    }
+}
+
+/// The structure in which a [`GptError`] was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Structure {
+    Mbr,
+    /// An extended boot record of a legacy MBR, see [`GptError::lba`] for its location.
+    Ebr,
+    Header(GptCopy),
+    PartEntryArray(GptCopy),
+    /// The partition entry at this index of the partition entry array.
+    PartEntry(u32),
+}
+
+impl Display for Structure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Mbr => write!(f, "MBR"),
+            Self::Ebr => write!(f, "EBR"),
+            Self::Header(GptCopy::Primary) => write!(f, "primary GPT header"),
+            Self::Header(GptCopy::Backup) => write!(f, "backup GPT header"),
+            Self::PartEntryArray(GptCopy::Primary) => write!(f, "primary partition entry array"),
+            Self::PartEntryArray(GptCopy::Backup) => write!(f, "backup partition entry array"),
+            Self::PartEntry(index) => write!(f, "partition entry {}", index),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GptError {
+    kind: ErrorKind,
+    structure: Option<Structure>,
+    lba: Option<u64>,
+    offset: Option<u64>,
+    mismatch: Option<(u64, u64)>,
+    #[cfg(feature = "std")]
+    io: Option<IoError>,
+}
+
+/// The OS error code or the kind of an I/O error, which unlike [`std::io::Error`] is `Copy`.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IoError {
+    Os(i32),
+    Kind(std::io::ErrorKind),
+}
+
+impl GptError {
+    pub fn new(kind: ErrorKind) -> Self {
+        Self {
+            kind,
+            structure: None,
+            lba: None,
+            offset: None,
+            mismatch: None,
+            #[cfg(feature = "std")]
+            io: None,
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// The structure in which the error was found.
+    pub fn structure(&self) -> Option<Structure> {
+        self.structure
+    }
+
+    /// The LBA from which the structure was read.
+    pub fn lba(&self) -> Option<u64> {
+        self.lba
+    }
+
+    /// Byte offset of the offending field from the start of the structure, or of the partition
+    /// entry array for partition entries.
+    pub fn offset(&self) -> Option<u64> {
+        self.offset
+    }
+
+    /// The expected value of the offending field.
+    pub fn expected(&self) -> Option<u64> {
+        self.mismatch.map(|(expected, _)| expected)
+    }
+
+    /// The value found in the offending field.
+    pub fn found(&self) -> Option<u64> {
+        self.mismatch.map(|(_, found)| found)
+    }
+
+    /// Record the byte offset of the offending field.
+    pub(crate) fn at(mut self, offset: usize) -> Self {
+        self.offset = Some(offset as u64);
+        self
+    }
+
+    /// Record the expected and the found value of the offending field.
+    pub(crate) fn mismatch(mut self, expected: u64, found: u64) -> Self {
+        self.mismatch = Some((expected, found));
+        self
+    }
+
+    /// Record that the error was found in `structure`, which starts `base` bytes into the
+    /// enclosing structure. An inner structure that is already recorded is kept.
+    pub(crate) fn inside(mut self, structure: Structure, base: usize) -> Self {
+        self.structure.get_or_insert(structure);
+        self.offset = self.offset.map(|offset| offset + base as u64);
+        self
+    }
+
+    /// Record the LBA from which the structure was read.
+    pub(crate) fn read_from(mut self, lba: u64) -> Self {
+        self.lba.get_or_insert(lba);
+        self
+    }
+}
+
+#[cfg(feature = "std")]
+impl GptError {
+    /// The I/O error of a [`ErrorKind::DevIo`] error, with its OS error code if it has one.
+    pub fn io_error(&self) -> Option<std::io::Error> {
+        self.io.map(|io| match io {
+            IoError::Os(code) => std::io::Error::from_raw_os_error(code),
+            IoError::Kind(kind) => kind.into(),
+        })
+    }
+}
+
+impl From<ErrorKind> for GptError {
+    fn from(kind: ErrorKind) -> Self {
+        Self::new(kind)
+    }
+}
+
+impl Display for GptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(structure) = self.structure {
+            write!(f, "{}", structure)?;
+            if let Some(lba) = self.lba {
+                write!(f, " at LBA {}", lba)?;
+            }
+            if let Some(offset) = self.offset {
+                write!(f, ", byte {}", offset)?;
+            }
+            write!(f, ": ")?;
+        }
+        write!(f, "{}", self.kind.message())?;
+        if let Some((expected, found)) = self.mismatch {
+            write!(f, " (expected {:#x}, found {:#x})", expected, found)?;
+        }
+        #[cfg(feature = "std")]
+        if let Some(err) = self.io_error() {
+            write!(f, ": {}", err)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for GptError {
+    fn from(err: std::io::Error) -> Self {
+        let io = match err.raw_os_error() {
+            Some(code) => IoError::Os(code),
+            None => IoError::Kind(err.kind()),
+        };
+        Self {
+            io: Some(io),
+            ..Self::new(ErrorKind::DevIo)
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for GptError {}
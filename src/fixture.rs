@@ -0,0 +1,45 @@
+//! Disks and tables shared by the unit tests.
+
+use crate::{
+    BlockDevice, GptBuilder, GuidPartTable, LogicalBlockSize, MemDevice, PartEntry, PartUUID,
+    PartitionType,
+};
+use alloc::{vec, vec::Vec};
+
+/// Number of logical blocks of the test disks, 8 MiB with 512-byte blocks.
+pub(crate) const NUM_BLOCKS: u64 = 16384;
+pub(crate) const LBS: LogicalBlockSize = LogicalBlockSize::Lb512;
+
+/// A zeroed disk of `num_blocks` logical blocks of `LBS` bytes.
+pub(crate) fn mem_device(num_blocks: u64) -> MemDevice<Vec<u8>> {
+    MemDevice::new(vec![0; num_blocks as usize * LBS as usize], LBS).unwrap()
+}
+
+/// An empty GPT with 128 entries for a disk of `NUM_BLOCKS` blocks, usable from LBA 34 to 16350.
+pub(crate) fn table() -> GuidPartTable {
+    GptBuilder::new(NUM_BLOCKS, LBS, PartUUID::from_u128(1))
+        .build()
+        .unwrap()
+}
+
+/// A partition of type `kind` at `starting_lba..=ending_lba`, with its starting LBA as GUID.
+pub(crate) fn partition(kind: PartitionType, starting_lba: u64, ending_lba: u64) -> PartEntry {
+    PartEntry {
+        part_type_guid: kind.guid,
+        part_guid: PartUUID::from_u128(starting_lba as _),
+        starting_lba,
+        ending_lba,
+        ..Default::default()
+    }
+}
+
+pub(crate) fn linux_partition(starting_lba: u64, ending_lba: u64) -> PartEntry {
+    partition(PartitionType::LINUX_FILESYSTEM, starting_lba, ending_lba)
+}
+
+/// The block at `lba` of `dev`.
+pub(crate) fn read_block<D: BlockDevice + ?Sized>(dev: &D, lba: u64) -> Vec<u8> {
+    let mut block = vec![0; dev.block_size() as usize];
+    dev.read_blocks(lba, &mut block).unwrap();
+    block
+}
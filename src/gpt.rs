@@ -1,4 +1,20 @@
-use crate::{Deserialize, GptError, Header, ProtectiveMbr, Serialize, entry::PartTableEntry};
+use crate::{
+    Alignment, BlockDevice, Deserialize, ErrorKind, GptError, Header, MbrKind, MbrReport,
+    PartAttributes, PartEntry, PartName, PartTableEntry, PartUUID, ProtectiveMbr, Serialize,
+    Structure,
+    hdr::{NUMBER_OF_PARTITION_ENTRIES_OFFSET, PRIMARY_HEADER_LBA},
+};
+use alloc::{vec, vec::Vec};
+use core::mem;
+
+const PROTECTIVE_MBR_LBA: u64 = 0;
+/// Largest partition entry array that is read from a disk or built, 131,072 entries of 128 bytes,
+/// so that a hostile header cannot make [`GuidPartTable::read`] allocate gigabytes.
+pub(crate) const MAX_PART_ENTRY_ARRAY_SIZE: usize = 16 << 20;
+
+/// HMAC label of the disk GUID derived by [`GuidPartTable::derive_guids`].
+#[cfg(feature = "derive")]
+const DISK_GUID_LABEL: &[u8] = b"disk-uuid";
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LogicalBlockSize {
@@ -6,13 +22,535 @@ pub enum LogicalBlockSize {
     Lb4096 = 4096,
 }
 
+/// Which copy of the GPT a [`GuidPartTable`] was loaded from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GptCopy {
+    /// The primary header at LBA 1 and its partition entry array.
+    Primary,
+    /// The backup header at the last LBA and its partition entry array.
+    Backup,
+}
+
+/// A range of logical blocks that is not used by any partition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FreeRegion {
+    pub starting_lba: u64,
+    pub ending_lba: u64,
+}
+
+impl FreeRegion {
+    /// Number of logical blocks of the region.
+    pub fn num_blocks(&self) -> u64 {
+        self.ending_lba - self.starting_lba + 1
+    }
+}
+
 pub struct GuidPartTable {
     lbs: LogicalBlockSize,
+    mbr: ProtectiveMbr,
+    header: Header,
+    backup: Header,
+    part_table: PartTableEntry,
+    copy: GptCopy,
+    consistent: bool,
+    mbr_report: Option<MbrReport>,
+    alignment: Alignment,
 }
 
 impl GuidPartTable {
-    pub fn new(lbs: LogicalBlockSize) -> Self {
-        Self { lbs }
+    pub fn new(
+        lbs: LogicalBlockSize,
+        mbr: ProtectiveMbr,
+        header: Header,
+        backup: Header,
+        part_table: PartTableEntry,
+    ) -> Self {
+        Self {
+            lbs,
+            mbr,
+            header,
+            backup,
+            part_table,
+            copy: GptCopy::Primary,
+            consistent: true,
+            mbr_report: None,
+            alignment: Alignment::default(),
+        }
+    }
+
+    /// Read the protective MBR, both GPT headers and both partition entry arrays from `dev`.
+    ///
+    /// The primary copy is used when it is valid, otherwise the backup copy is used.
+    /// When the two copies disagree, the other copy is rebuilt from the one in use.
+    /// An error is returned only when neither copy is valid, or when the rebuilt copy would not
+    /// fit next to the usable area.
+    ///
+    /// A primary header whose backup is not at the last LBA, e.g. on an image that grew after
+    /// partitioning, is still used and its backup read from its alternate LBA. The table is then
+    /// inconsistent, the rebuilt backup copy is moved to the end of the disk and the 0xEE record
+    /// of a protective MBR is resized to cover the whole disk.
+    ///
+    /// LBA 0 is classified by [`MbrReport::classify`] and does not prevent reading the GPT. A
+    /// protective or hybrid MBR is kept as it is, whatever rules it breaks. A legacy MBR is
+    /// replaced by a new protective MBR with the same boot code, and a sector that is not an MBR
+    /// by a new protective MBR. The classification is kept in [`GuidPartTable::mbr_report`].
+    pub fn read<D: BlockDevice + ?Sized>(dev: &D) -> Result<Self, GptError> {
+        let lbs = dev.block_size();
+        let num_blocks = dev.num_blocks();
+        let mut block = vec![0; lbs as usize];
+
+        let mbr_report = dev
+            .read_blocks(PROTECTIVE_MBR_LBA, &mut block)
+            .and_then(|_| MbrReport::classify(&block, num_blocks))
+            .unwrap_or_else(|err| {
+                MbrReport::unreadable(err.inside(Structure::Mbr, 0).read_from(PROTECTIVE_MBR_LBA))
+            });
+        let mut mbr = match mbr_report.kind() {
+            MbrKind::Protective | MbrKind::Hybrid => ProtectiveMbr::from_sector(&block),
+            MbrKind::Legacy => ProtectiveMbr::new(num_blocks)
+                .with_boot_code(ProtectiveMbr::from_sector(&block).boot_code),
+            MbrKind::NotMbr => ProtectiveMbr::new(num_blocks),
+        };
+
+        let primary = Self::read_copy(dev, PRIMARY_HEADER_LBA);
+        let backup_lba = match &primary {
+            Ok((header, _)) => header.alternate_lba,
+            Err(_) => num_blocks.saturating_sub(1),
+        };
+        let backup = Self::read_copy(dev, backup_lba);
+
+        let consistent = match (&primary, &backup) {
+            (Ok((primary_hdr, primary_table)), Ok((backup_hdr, backup_table))) => {
+                primary_hdr.is_alternate_of(backup_hdr)
+                    && primary_hdr.alternate_lba == num_blocks - 1
+                    && primary_table.entries == backup_table.entries
+            }
+            _ => false,
+        };
+        let (copy, header, backup, part_table) = match (primary, backup) {
+            (Ok((header, part_table)), Ok((backup, _))) if consistent => {
+                (GptCopy::Primary, header, backup, part_table)
+            }
+            (Ok((mut header, part_table)), _) => {
+                if header.alternate_lba != num_blocks - 1
+                    && mbr_report.kind() == MbrKind::Protective
+                {
+                    mbr.resize(num_blocks);
+                }
+                header.alternate_lba = num_blocks - 1;
+                header.update_crc32();
+                let array_blocks = (header.part_entry_array_size() as u64).div_ceil(lbs as u64);
+                let backup = header.alternate(header.alternate_lba.saturating_sub(array_blocks));
+                backup
+                    .validate(header.alternate_lba, num_blocks, lbs)
+                    .map_err(|err| {
+                        err.inside(Structure::Header(GptCopy::Backup), 0)
+                            .read_from(header.alternate_lba)
+                    })?;
+                (GptCopy::Primary, header, backup, part_table)
+            }
+            (Err(_), Ok((backup, part_table))) => {
+                let header = backup.alternate(PRIMARY_HEADER_LBA + 1);
+                header
+                    .validate(PRIMARY_HEADER_LBA, num_blocks, lbs)
+                    .map_err(|err| {
+                        err.inside(Structure::Header(GptCopy::Primary), 0)
+                            .read_from(PRIMARY_HEADER_LBA)
+                    })?;
+                (GptCopy::Backup, header, backup, part_table)
+            }
+            (Err(err), Err(_)) => return Err(err),
+        };
+
+        Ok(Self {
+            copy,
+            consistent,
+            mbr_report: Some(mbr_report),
+            ..Self::new(lbs, mbr, header, backup, part_table)
+        })
+    }
+
+    /// Write both partition entry arrays, both GPT headers and the protective MBR to `dev`.
+    ///
+    /// The partition entry array CRC and both header CRCs are recomputed before writing.
+    /// Nothing is written unless the table fits the size of `dev` and both headers are valid.
+    /// The backup copy is written first, so that the primary header never points to a backup
+    /// that is not on the disk yet.
+    pub fn write<D: BlockDevice + ?Sized>(&mut self, dev: &mut D) -> Result<(), GptError> {
+        if dev.block_size() != self.lbs {
+            return Err(GptError::new(ErrorKind::DevBlockSize));
+        }
+        let num_blocks = dev.num_blocks();
+        if self.header.alternate_lba.checked_add(1) != Some(num_blocks) {
+            return Err(GptError::new(ErrorKind::DevNumBlocks)
+                .mismatch(self.header.alternate_lba.saturating_add(1), num_blocks));
+        }
+        self.header
+            .validate(PRIMARY_HEADER_LBA, num_blocks, self.lbs)
+            .map_err(|err| err.inside(Structure::Header(GptCopy::Primary), 0))?;
+        self.backup
+            .validate(self.header.alternate_lba, num_blocks, self.lbs)
+            .map_err(|err| err.inside(Structure::Header(GptCopy::Backup), 0))?;
+        let num_part_entries = self.part_table.entries.len();
+        if num_part_entries != self.header.num_part_entries as usize
+            || num_part_entries != self.backup.num_part_entries as usize
+        {
+            return Err(GptError::new(ErrorKind::PartEntryCount)
+                .mismatch(self.header.num_part_entries as _, num_part_entries as _));
+        }
+
+        let lbs = self.lbs as usize;
+        let mut array = self
+            .part_table
+            .serialize_part_entries(self.header.part_entry_size as _);
+        let crc32 = crate::crc32(&array);
+        for header in [&mut self.header, &mut self.backup] {
+            header.crc32_part_entry_array = crc32;
+            header.update_crc32();
+        }
+        array.resize(array.len().div_ceil(lbs) * lbs, 0);
+
+        dev.write_blocks(self.backup.part_entry_lba, &array)?;
+        dev.write_blocks(self.backup.my_lba, &self.serialize_header(&self.backup))?;
+        dev.write_blocks(self.header.part_entry_lba, &array)?;
+        dev.write_blocks(self.header.my_lba, &self.serialize_header(&self.header))?;
+        dev.write_blocks(PROTECTIVE_MBR_LBA, &self.serialize_mbr(&self.mbr))?;
+        self.consistent = true;
+        Ok(())
+    }
+
+    /// Read a GPT header at `lba` and the partition entry array it describes.
+    fn read_copy<D: BlockDevice + ?Sized>(
+        dev: &D,
+        lba: u64,
+    ) -> Result<(Header, PartTableEntry), GptError> {
+        let copy = if lba == PRIMARY_HEADER_LBA {
+            GptCopy::Primary
+        } else {
+            GptCopy::Backup
+        };
+        let lbs = dev.block_size() as usize;
+        let mut block = vec![0; lbs];
+        let header = dev
+            .read_blocks(lba, &mut block)
+            .and_then(|_| Header::deserialize(&block))
+            .and_then(|header| {
+                match header.validate(lba, dev.num_blocks(), dev.block_size()) {
+                    // The backup header is not at the end of a disk that grew. The alternate LBA
+                    // is checked last, so the rest of the header is valid.
+                    Err(err)
+                        if err.kind() == ErrorKind::HdrAlternateLba
+                            && copy == GptCopy::Primary
+                            && header.alternate_lba < dev.num_blocks() =>
+                    {
+                        Ok(header)
+                    }
+                    result => result.map(|_| header),
+                }
+            })
+            .map_err(|err| err.inside(Structure::Header(copy), 0).read_from(lba))?;
+
+        let array_size = header.part_entry_array_size();
+        if array_size > MAX_PART_ENTRY_ARRAY_SIZE {
+            return Err(GptError::new(ErrorKind::HdrPartEntryArraySize)
+                .at(NUMBER_OF_PARTITION_ENTRIES_OFFSET)
+                .mismatch(MAX_PART_ENTRY_ARRAY_SIZE as _, array_size as _)
+                .inside(Structure::Header(copy), 0)
+                .read_from(lba));
+        }
+        let mut array = vec![0; array_size.div_ceil(lbs) * lbs];
+        let part_table = dev
+            .read_blocks(header.part_entry_lba, &mut array)
+            .and_then(|_| {
+                PartTableEntry::generate_part_entries(
+                    &array[..array_size],
+                    header.part_entry_size as _,
+                    header.crc32_part_entry_array,
+                    header.first_usable_lba..=header.last_usable_lba,
+                )
+            })
+            .map_err(|err| {
+                err.inside(Structure::PartEntryArray(copy), 0)
+                    .read_from(header.part_entry_lba)
+            })?;
+        Ok((header, part_table))
+    }
+
+    /// The copy of the GPT that was loaded, the other copy is rebuilt from it when needed.
+    pub fn copy(&self) -> GptCopy {
+        self.copy
+    }
+
+    /// Whether the primary and backup copies are both valid and describe the same table.
+    pub fn is_consistent(&self) -> bool {
+        self.consistent
+    }
+
+    /// The classification of LBA 0 and the rules it breaks, if the table was read from a disk.
+    pub fn mbr_report(&self) -> Option<&MbrReport> {
+        self.mbr_report.as_ref()
+    }
+
+    /// Add `entry` in the first unused slot of the partition entry array and return its index.
+    pub fn add_partition(&mut self, entry: PartEntry) -> Result<usize, GptError> {
+        let index = self
+            .part_table
+            .entries
+            .iter()
+            .position(|entry| !entry.is_used())
+            .ok_or(GptError::new(ErrorKind::PartTableFull))?;
+        self.add_partition_at(index, entry)?;
+        Ok(index)
+    }
+
+    /// Add `entry` in the unused slot `index` of the partition entry array.
+    pub fn add_partition_at(&mut self, index: usize, entry: PartEntry) -> Result<(), GptError> {
+        let slot = self.part_table.entries.get(index).ok_or(
+            GptError::new(ErrorKind::PartIndex).inside(Structure::PartEntry(index as _), 0),
+        )?;
+        if slot.is_used() {
+            return Err(
+                GptError::new(ErrorKind::PartSlotUsed).inside(Structure::PartEntry(index as _), 0)
+            );
+        }
+        if !entry.is_used() {
+            return Err(GptError::new(ErrorKind::PartTypeUnused));
+        }
+        self.check_part_range(index, entry.starting_lba, entry.ending_lba)?;
+        self.part_table.entries[index] = entry;
+        Ok(())
+    }
+
+    /// Add `entry` with a size of `num_blocks` in the first free space that fits and return its
+    /// index.
+    ///
+    /// The starting LBA is rounded up to the alignment of the table. To align the end of the
+    /// partition too, round `num_blocks` up with [`Alignment::align_up`].
+    pub fn allocate_partition(
+        &mut self,
+        mut entry: PartEntry,
+        num_blocks: u64,
+    ) -> Result<usize, GptError> {
+        if num_blocks == 0 {
+            return Err(GptError::new(ErrorKind::PartRange));
+        }
+        let region = self
+            .free_regions(Some(self.alignment), num_blocks)
+            .into_iter()
+            .next()
+            .ok_or(GptError::new(ErrorKind::PartNoSpace))?;
+        entry.starting_lba = region.starting_lba;
+        entry.ending_lba = region.starting_lba + num_blocks - 1;
+        self.add_partition(entry)
+    }
+
+    /// Indexes of the partitions whose boundaries are not aligned to `alignment`, e.g. the
+    /// physical block size of the disk.
+    pub fn misaligned_partitions(&self, alignment: Alignment) -> Vec<usize> {
+        self.part_table
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| {
+                entry.is_used()
+                    && !(alignment.is_aligned(entry.starting_lba, self.lbs)
+                        && alignment.is_aligned(entry.ending_lba + 1, self.lbs))
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Delete the partition at `index` and return its entry.
+    pub fn delete_partition(&mut self, index: usize) -> Result<PartEntry, GptError> {
+        self.used_part_mut(index)?;
+        Ok(mem::take(&mut self.part_table.entries[index]))
+    }
+
+    /// Move the partition at `index` to `starting_lba..=ending_lba`.
+    pub fn resize_partition(
+        &mut self,
+        index: usize,
+        starting_lba: u64,
+        ending_lba: u64,
+    ) -> Result<(), GptError> {
+        self.used_part_mut(index)?;
+        self.check_part_range(index, starting_lba, ending_lba)?;
+        let entry = &mut self.part_table.entries[index];
+        entry.starting_lba = starting_lba;
+        entry.ending_lba = ending_lba;
+        Ok(())
+    }
+
+    pub fn set_partition_type(
+        &mut self,
+        index: usize,
+        part_type_guid: PartUUID,
+    ) -> Result<(), GptError> {
+        if part_type_guid.iter().all(|byte| *byte == 0) {
+            return Err(GptError::new(ErrorKind::PartTypeUnused));
+        }
+        self.used_part_mut(index)?.part_type_guid = part_type_guid;
+        Ok(())
+    }
+
+    pub fn set_partition_name(&mut self, index: usize, name: PartName) -> Result<(), GptError> {
+        self.used_part_mut(index)?.name = name;
+        Ok(())
+    }
+
+    pub fn set_partition_attributes(
+        &mut self,
+        index: usize,
+        attributes: PartAttributes,
+    ) -> Result<(), GptError> {
+        self.used_part_mut(index)?.attributes = attributes;
+        Ok(())
+    }
+
+    /// Replace the disk GUID and the GUIDs of all used partitions with GUIDs derived from `seed`,
+    /// see [`PartUUID::derive`].
+    ///
+    /// The labels follow systemd-repart: `disk-uuid` for the disk, and for a partition the type
+    /// GUID in RFC 4122 byte order, followed by the little endian instance number for all but the
+    /// first partition of that type.
+    #[cfg(feature = "derive")]
+    pub fn derive_guids(&mut self, seed: &[u8]) {
+        let disk_guid = PartUUID::derive(seed, DISK_GUID_LABEL);
+        self.header.disk_guid = disk_guid;
+        self.backup.disk_guid = disk_guid;
+        let entries = &mut self.part_table.entries;
+        for index in 0..entries.len() {
+            if !entries[index].is_used() {
+                continue;
+            }
+            let part_type_guid = entries[index].part_type_guid;
+            let instance = entries[..index]
+                .iter()
+                .filter(|entry| entry.part_type_guid == part_type_guid)
+                .count() as u64;
+            let mut label = [0; 24];
+            label[..16].copy_from_slice(&part_type_guid.to_u128().to_be_bytes());
+            label[16..].copy_from_slice(&instance.to_le_bytes());
+            let len = if instance == 0 { 16 } else { 24 };
+            entries[index].part_guid = PartUUID::derive(seed, &label[..len]);
+        }
+    }
+
+    fn used_part_mut(&mut self, index: usize) -> Result<&mut PartEntry, GptError> {
+        self.part_table
+            .entries
+            .get_mut(index)
+            .filter(|entry| entry.is_used())
+            .ok_or(GptError::new(ErrorKind::PartIndex).inside(Structure::PartEntry(index as _), 0))
+    }
+
+    /// Check that `starting_lba..=ending_lba` is usable and not used by any partition except the
+    /// one at `index`.
+    fn check_part_range(
+        &self,
+        index: usize,
+        starting_lba: u64,
+        ending_lba: u64,
+    ) -> Result<(), GptError> {
+        if starting_lba > ending_lba
+            || starting_lba < self.header.first_usable_lba
+            || ending_lba > self.header.last_usable_lba
+        {
+            return Err(GptError::new(ErrorKind::PartRange));
+        }
+        let overlap = self
+            .part_table
+            .entries
+            .iter()
+            .enumerate()
+            .position(|(other, entry)| {
+                other != index && entry.is_used() && entry.overlaps(starting_lba, ending_lba)
+            });
+        match overlap {
+            Some(other) => {
+                Err(GptError::new(ErrorKind::PartOverlap)
+                    .inside(Structure::PartEntry(other as _), 0))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Regions of the usable area that are not used by any partition, in ascending order.
+    ///
+    /// With `alignment`, the starting LBA of each region is rounded up to it. Regions smaller
+    /// than `min_blocks` logical blocks are dropped.
+    pub fn free_regions(&self, alignment: Option<Alignment>, min_blocks: u64) -> Vec<FreeRegion> {
+        let mut used = self
+            .part_table
+            .entries
+            .iter()
+            .filter(|entry| entry.is_used())
+            .map(|entry| (entry.starting_lba, entry.ending_lba))
+            .collect::<Vec<_>>();
+        used.sort_unstable();
+
+        let (first, last) = (self.header.first_usable_lba, self.header.last_usable_lba);
+        let mut regions = Vec::new();
+        let mut next = first;
+        for (start, end) in used.into_iter().chain([(last.saturating_add(1), u64::MAX)]) {
+            if start > next && next <= last {
+                regions.push(FreeRegion {
+                    starting_lba: next,
+                    ending_lba: (start - 1).min(last),
+                });
+            }
+            next = next.max(end.saturating_add(1));
+        }
+
+        regions
+            .into_iter()
+            .filter_map(|region| match alignment {
+                Some(alignment) => Some(FreeRegion {
+                    starting_lba: alignment
+                        .align_up(region.starting_lba, self.lbs)
+                        .filter(|lba| *lba <= region.ending_lba)?,
+                    ..region
+                }),
+                None => Some(region),
+            })
+            .filter(|region| region.num_blocks() >= min_blocks)
+            .collect()
+    }
+
+    /// Alignment used by [`GuidPartTable::allocate_partition`], 1 MiB by default.
+    pub fn alignment(&self) -> Alignment {
+        self.alignment
+    }
+
+    pub fn set_alignment(&mut self, alignment: Alignment) {
+        self.alignment = alignment;
+    }
+
+    pub fn block_size(&self) -> LogicalBlockSize {
+        self.lbs
+    }
+
+    pub fn mbr(&self) -> &ProtectiveMbr {
+        &self.mbr
+    }
+
+    /// Replace the MBR written to LBA 0, e.g. with one built by [`ProtectiveMbr::new`].
+    pub fn set_mbr(&mut self, mbr: ProtectiveMbr) {
+        self.mbr = mbr;
+    }
+
+    /// The primary GPT header.
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// The backup GPT header.
+    pub fn backup_header(&self) -> &Header {
+        &self.backup
+    }
+
+    pub fn part_table(&self) -> &PartTableEntry {
+        &self.part_table
     }
 
     pub fn parse_header(&self, data: &[u8]) -> Result<Header, GptError> {
@@ -31,12 +569,27 @@ impl GuidPartTable {
         mbr.serialize(self.lbs as _)
     }
 
+    /// Parse the partition entry array described by `header`.
+    ///
+    /// `data` must hold at least `num_part_entries * part_entry_size` bytes, otherwise
+    /// [`ErrorKind::Truncated`] is returned.
     pub fn parse_part_table(
         &self,
         data: &[u8],
-        part_entry_size: usize,
+        header: &Header,
     ) -> Result<PartTableEntry, GptError> {
-        PartTableEntry::generate_part_entries(data, part_entry_size)
+        let array_size = header.part_entry_array_size();
+        if data.len() < array_size {
+            return Err(
+                GptError::new(ErrorKind::Truncated).mismatch(array_size as _, data.len() as _)
+            );
+        }
+        PartTableEntry::generate_part_entries(
+            &data[..array_size],
+            header.part_entry_size as _,
+            header.crc32_part_entry_array,
+            header.first_usable_lba..=header.last_usable_lba,
+        )
     }
 
     pub fn serialize_parse_entries(
@@ -47,3 +600,594 @@ impl GuidPartTable {
         part_table.serialize_part_entries(part_entry_size)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        GptBuilder, MemDevice, PartitionType,
+        fixture::{LBS, NUM_BLOCKS, linux_partition, mem_device, read_block, table},
+    };
+    use alloc::string::ToString;
+
+    /// A disk of `NUM_BLOCKS` blocks with one partition at LBA 2048..=4095.
+    fn disk() -> MemDevice<Vec<u8>> {
+        let mut dev = mem_device(NUM_BLOCKS);
+        let mut table = table();
+        table.add_partition(linux_partition(2048, 4095)).unwrap();
+        table.write(&mut dev).unwrap();
+        dev
+    }
+
+    fn read_header(dev: &MemDevice<Vec<u8>>, lba: u64) -> Header {
+        Header::deserialize(&read_block(dev, lba)).unwrap()
+    }
+
+    #[test]
+    fn read_primary() {
+        let table = GuidPartTable::read(&disk()).unwrap();
+        assert_eq!(table.copy(), GptCopy::Primary);
+        assert!(table.is_consistent());
+        assert!(table.mbr_report().unwrap().is_valid());
+        assert_eq!(table.header().alternate_lba, NUM_BLOCKS - 1);
+        assert_eq!(table.part_table().entries[0], linux_partition(2048, 4095));
+    }
+
+    #[test]
+    fn read_falls_back_to_backup() {
+        let mut dev = disk();
+        dev.write_blocks(PRIMARY_HEADER_LBA, &[0; LBS as usize])
+            .unwrap();
+        let table = GuidPartTable::read(&dev).unwrap();
+        assert_eq!(table.copy(), GptCopy::Backup);
+        assert!(!table.is_consistent());
+        assert_eq!(table.header().my_lba, PRIMARY_HEADER_LBA);
+        assert_eq!(table.header().part_entry_lba, 2);
+        assert_eq!(table.part_table().entries[0], linux_partition(2048, 4095));
+    }
+
+    #[test]
+    fn read_falls_back_on_corrupt_entry_array() {
+        let mut dev = disk();
+        let mut block = read_block(&dev, 2);
+        block[56] ^= 0xFF;
+        dev.write_blocks(2, &block).unwrap();
+
+        let table = GuidPartTable::read(&dev).unwrap();
+        assert_eq!(table.copy(), GptCopy::Backup);
+        assert!(!table.is_consistent());
+        assert_eq!(table.part_table().entries[0], linux_partition(2048, 4095));
+        assert_eq!(table.header().part_entry_lba, 2);
+    }
+
+    #[test]
+    fn read_fails_without_any_copy() {
+        let mut dev = disk();
+        dev.write_blocks(PRIMARY_HEADER_LBA, &[0; LBS as usize])
+            .unwrap();
+        dev.write_blocks(NUM_BLOCKS - 1, &[0; LBS as usize])
+            .unwrap();
+        let err = GuidPartTable::read(&dev).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::HdrSignature);
+        assert_eq!(err.structure(), Some(Structure::Header(GptCopy::Primary)));
+    }
+
+    #[test]
+    fn read_with_zeroed_mbr() {
+        let mut dev = disk();
+        dev.write_blocks(PROTECTIVE_MBR_LBA, &[0; LBS as usize])
+            .unwrap();
+        let table = GuidPartTable::read(&dev).unwrap();
+        assert!(table.is_consistent());
+        let report = table.mbr_report().unwrap();
+        assert_eq!(report.kind(), MbrKind::NotMbr);
+        assert_eq!(report.violations()[0].kind(), ErrorKind::MbrSignature);
+        assert_eq!(table.mbr(), &ProtectiveMbr::new(NUM_BLOCKS));
+    }
+
+    #[test]
+    fn read_grown_disk() {
+        let mut data = disk().into_inner();
+        data.resize(20480 * LBS as usize, 0);
+        let mut dev = MemDevice::new(data, LBS).unwrap();
+
+        let mut table = GuidPartTable::read(&dev).unwrap();
+        assert_eq!(table.copy(), GptCopy::Primary);
+        assert!(!table.is_consistent());
+        assert_eq!(table.header().alternate_lba, 20479);
+        assert_eq!(table.backup_header().my_lba, 20479);
+        assert_eq!(table.backup_header().part_entry_lba, 20447);
+
+        assert_eq!(table.mbr(), &ProtectiveMbr::new(20480));
+
+        table.write(&mut dev).unwrap();
+        let table = GuidPartTable::read(&dev).unwrap();
+        assert!(table.is_consistent());
+        assert!(table.mbr_report().unwrap().is_valid());
+        assert_eq!(table.part_table().entries[0], linux_partition(2048, 4095));
+    }
+
+    #[test]
+    fn read_grown_disk_keeps_boot_code() {
+        let mut dev = mem_device(NUM_BLOCKS);
+        let mut table = table();
+        let mbr = ProtectiveMbr::new(NUM_BLOCKS)
+            .with_boot_code([0x90; 440])
+            .with_boot_indicator(true);
+        table.set_mbr(mbr);
+        table.write(&mut dev).unwrap();
+        let mut data = dev.into_inner();
+        data.resize(20480 * LBS as usize, 0);
+
+        let table = GuidPartTable::read(&MemDevice::new(data, LBS).unwrap()).unwrap();
+        let expected = ProtectiveMbr::new(20480)
+            .with_boot_code([0x90; 440])
+            .with_boot_indicator(true);
+        assert_eq!(table.mbr(), &expected);
+    }
+
+    #[test]
+    fn read_rejects_rebuilt_primary_inside_usable_area() {
+        let mut dev = disk();
+        let mut backup = read_header(&dev, NUM_BLOCKS - 1);
+        backup.first_usable_lba = 2;
+        backup.update_crc32();
+        dev.write_blocks(NUM_BLOCKS - 1, &backup.serialize(LBS as _))
+            .unwrap();
+        dev.write_blocks(PRIMARY_HEADER_LBA, &[0; LBS as usize])
+            .unwrap();
+
+        let err = GuidPartTable::read(&dev).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::HdrPartEntryArray);
+        assert_eq!(err.structure(), Some(Structure::Header(GptCopy::Primary)));
+    }
+
+    #[test]
+    fn write_checks_before_writing() {
+        let mut table = GuidPartTable::read(&disk()).unwrap();
+        let mut dev = mem_device(8192);
+        let err = table.write(&mut dev).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::DevNumBlocks);
+        assert_eq!(
+            (err.expected(), err.found()),
+            (Some(NUM_BLOCKS), Some(8192))
+        );
+        assert!(dev.into_inner().iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn write_rejects_entry_count_mismatch() {
+        let table = GuidPartTable::read(&disk()).unwrap();
+        let mut table = GuidPartTable::new(
+            LBS,
+            table.mbr().clone(),
+            table.header().clone(),
+            table.backup_header().clone(),
+            PartTableEntry {
+                entries: vec![linux_partition(2048, 4095)],
+            },
+        );
+        let mut dev = mem_device(NUM_BLOCKS);
+        let err = table.write(&mut dev).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::PartEntryCount);
+        assert_eq!((err.expected(), err.found()), (Some(128), Some(1)));
+    }
+
+    #[test]
+    fn write_round_trip() {
+        let mut dev = disk();
+        let mut table = GuidPartTable::read(&dev).unwrap();
+        table.add_partition(linux_partition(4096, 8191)).unwrap();
+        table.write(&mut dev).unwrap();
+
+        let table = GuidPartTable::read(&dev).unwrap();
+        assert!(table.is_consistent());
+        assert_eq!(table.part_table().entries[1], linux_partition(4096, 8191));
+        let backup = read_header(&dev, NUM_BLOCKS - 1);
+        assert!(table.header().is_alternate_of(&backup));
+    }
+
+    #[test]
+    fn read_rejects_huge_entry_array() {
+        let num_blocks = 65536;
+        let mut dev = mem_device(num_blocks);
+        GptBuilder::new(num_blocks, LBS, PartUUID::from_u128(1))
+            .build()
+            .unwrap()
+            .write(&mut dev)
+            .unwrap();
+        // 140,000 entries in LBA 2..=35001, in front of the usable area.
+        let mut header = read_header(&dev, PRIMARY_HEADER_LBA);
+        header.num_part_entries = 140_000;
+        header.first_usable_lba = 40000;
+        header.update_crc32();
+        dev.write_blocks(PRIMARY_HEADER_LBA, &header.serialize(LBS as _))
+            .unwrap();
+        header
+            .validate(PRIMARY_HEADER_LBA, num_blocks, LBS)
+            .unwrap();
+
+        let err = GuidPartTable::read_copy(&dev, PRIMARY_HEADER_LBA)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::HdrPartEntryArraySize);
+        assert_eq!(err.structure(), Some(Structure::Header(GptCopy::Primary)));
+    }
+
+    #[test]
+    fn read_hybrid_mbr_with_disk_signature() {
+        let mut dev = disk();
+        let mut table = GuidPartTable::read(&dev).unwrap();
+        let hybrid = table
+            .hybrid_mbr(&[crate::HybridPart {
+                index: 0,
+                os_type: 0x83,
+                active: true,
+            }])
+            .unwrap();
+        table.set_mbr(hybrid.clone());
+        table.write(&mut dev).unwrap();
+        let mut block = read_block(&dev, PROTECTIVE_MBR_LBA);
+        block[440..444].copy_from_slice(&0x12345678_u32.to_le_bytes());
+        dev.write_blocks(PROTECTIVE_MBR_LBA, &block).unwrap();
+
+        let table = GuidPartTable::read(&dev).unwrap();
+        let report = table.mbr_report().unwrap();
+        assert_eq!(report.kind(), MbrKind::Hybrid);
+        assert!(report.is_valid(), "{:?}", report.violations());
+        assert_eq!(table.mbr().part_records, hybrid.part_records);
+        assert_eq!(table.serialize_mbr(table.mbr()), block);
+    }
+
+    /// The layout created by systemd-repart 252 on a 64 MiB image with
+    /// `--seed=b0f7b4d2-9a1e-4d3c-8f5a-2e6c1d0a9b37`: a 16 MiB ESP and two 8 MiB
+    /// `linux-generic` partitions.
+    #[cfg(feature = "derive")]
+    fn repart_table() -> GuidPartTable {
+        let mut table = GptBuilder::new(131072, LBS, PartUUID::default())
+            .build()
+            .unwrap();
+        for (kind, starting_lba, ending_lba) in [
+            (PartitionType::EFI_SYSTEM, 2048, 34815),
+            (PartitionType::LINUX_FILESYSTEM, 34816, 51199),
+            (PartitionType::LINUX_FILESYSTEM, 51200, 67583),
+        ] {
+            table
+                .add_partition(PartEntry {
+                    part_type_guid: kind.guid,
+                    ..linux_partition(starting_lba, ending_lba)
+                })
+                .unwrap();
+        }
+        let seed: PartUUID = "b0f7b4d2-9a1e-4d3c-8f5a-2e6c1d0a9b37".parse().unwrap();
+        table.derive_guids(&seed.to_u128().to_be_bytes());
+        table
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_guids_like_systemd_repart() {
+        let table = repart_table();
+        let guid = |text: &str| text.parse::<PartUUID>().unwrap();
+        assert_eq!(
+            table.header().disk_guid,
+            guid("e6740b0b-8ed7-412d-bbfe-b642bdbf7f62")
+        );
+        assert_eq!(table.backup_header().disk_guid, table.header().disk_guid);
+        let part_guids = table.part_table().entries[..3]
+            .iter()
+            .map(|entry| entry.part_guid)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            part_guids,
+            [
+                guid("a6d5a756-09d5-47ca-9256-12bcbf93e5d8"),
+                guid("9d7e6613-d7bb-453e-b2e1-cf3ae3531dcb"),
+                guid("09e13c4b-797a-46a8-938d-12b025b92ec2"),
+            ]
+        );
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derived_images_are_identical() {
+        let image = || {
+            let mut dev = mem_device(131072);
+            repart_table().write(&mut dev).unwrap();
+            dev.into_inner()
+        };
+        assert!(image() == image());
+    }
+
+    #[test]
+    fn parse_truncated_part_table() {
+        let table = table();
+        let err = table
+            .parse_part_table(&[0; 128], table.header())
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::Truncated);
+        assert_eq!((err.expected(), err.found()), (Some(128 * 128), Some(128)));
+    }
+
+    /// An empty table with partitions at LBA 2048..=4095 in slot 0 and 8192..=12287 in slot 1.
+    fn edit_table() -> GuidPartTable {
+        let mut table = table();
+        assert_eq!(table.add_partition(linux_partition(2048, 4095)), Ok(0));
+        assert_eq!(table.add_partition(linux_partition(8192, 12287)), Ok(1));
+        table
+    }
+
+    #[test]
+    fn add_partition_in_first_unused_slot() {
+        let mut table = edit_table();
+        table
+            .add_partition_at(3, linux_partition(34, 2047))
+            .unwrap();
+        assert_eq!(table.add_partition(linux_partition(4096, 8191)), Ok(2));
+        assert_eq!(table.add_partition(linux_partition(12288, 16350)), Ok(4));
+        assert_eq!(table.part_table().entries[3], linux_partition(34, 2047));
+    }
+
+    #[test]
+    fn add_partition_errors() {
+        let mut table = edit_table();
+        let err = table
+            .add_partition_at(1, linux_partition(4096, 8191))
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::PartSlotUsed);
+        assert_eq!(err.structure(), Some(Structure::PartEntry(1)));
+
+        let unused = PartEntry {
+            part_type_guid: PartUUID::default(),
+            ..linux_partition(4096, 8191)
+        };
+        let err = table.add_partition(unused).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::PartTypeUnused);
+
+        let err = table
+            .add_partition_at(128, linux_partition(4096, 8191))
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::PartIndex);
+        assert!(!table.part_table().entries[2].is_used());
+    }
+
+    #[test]
+    fn partition_inside_usable_range() {
+        let mut table = table();
+        let (first, last) = (
+            table.header().first_usable_lba,
+            table.header().last_usable_lba,
+        );
+        for (starting_lba, ending_lba) in [(first - 1, 2047), (2048, last + 1), (4095, 2048)] {
+            let err = table
+                .add_partition(linux_partition(starting_lba, ending_lba))
+                .err()
+                .unwrap();
+            assert_eq!(err.kind(), ErrorKind::PartRange);
+        }
+        table.add_partition(linux_partition(first, first)).unwrap();
+        table.add_partition(linux_partition(last, last)).unwrap();
+    }
+
+    #[test]
+    fn partition_overlaps_neighbour() {
+        let mut table = edit_table();
+        for (starting_lba, ending_lba) in [(4095, 4096), (34, 2048), (8191, 8192), (3000, 9000)] {
+            let err = table
+                .add_partition(linux_partition(starting_lba, ending_lba))
+                .err()
+                .unwrap();
+            assert_eq!(err.kind(), ErrorKind::PartOverlap);
+            let neighbour = if starting_lba < 4096 { 0 } else { 1 };
+            assert_eq!(err.structure(), Some(Structure::PartEntry(neighbour)));
+        }
+        table.add_partition(linux_partition(4096, 8191)).unwrap();
+    }
+
+    #[test]
+    fn resize_partition() {
+        let mut table = edit_table();
+        // The new range overlaps the old range of the same partition.
+        table.resize_partition(0, 3000, 8191).unwrap();
+        let entry = &table.part_table().entries[0];
+        assert_eq!((entry.starting_lba, entry.ending_lba), (3000, 8191));
+        let err = table.resize_partition(0, 3000, 8192).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::PartOverlap);
+        assert_eq!(err.structure(), Some(Structure::PartEntry(1)));
+        let err = table.resize_partition(1, 8192, 16351).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::PartRange);
+        assert_eq!(table.part_table().entries[1], linux_partition(8192, 12287));
+    }
+
+    #[test]
+    fn edit_unused_slot() {
+        let mut table = edit_table();
+        let name = PartName::try_from("data").unwrap();
+        for index in [2, 128] {
+            for err in [
+                table.delete_partition(index).err(),
+                table.resize_partition(index, 4096, 8191).err(),
+                table
+                    .set_partition_type(index, PartitionType::LINUX_HOME.guid)
+                    .err(),
+                table.set_partition_name(index, name.clone()).err(),
+                table
+                    .set_partition_attributes(index, PartAttributes::default())
+                    .err(),
+            ] {
+                let err = err.unwrap();
+                assert_eq!(err.kind(), ErrorKind::PartIndex);
+                assert_eq!(err.structure(), Some(Structure::PartEntry(index as _)));
+            }
+        }
+    }
+
+    #[test]
+    fn edit_partition() {
+        let mut table = edit_table();
+        table
+            .set_partition_type(1, PartitionType::LINUX_HOME.guid)
+            .unwrap();
+        table
+            .set_partition_name(1, PartName::try_from("home").unwrap())
+            .unwrap();
+        table
+            .set_partition_attributes(1, PartAttributes::from_bits(1 << 63))
+            .unwrap();
+        let err = table
+            .set_partition_type(1, PartUUID::default())
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::PartTypeUnused);
+
+        let entry = &table.part_table().entries[1];
+        assert_eq!(entry.kind(), Some(PartitionType::LINUX_HOME));
+        assert_eq!(entry.name.to_string(), "home");
+        assert_eq!(entry.attributes.bits(), 1 << 63);
+
+        let deleted = table.delete_partition(0).unwrap();
+        assert_eq!(deleted, linux_partition(2048, 4095));
+        assert!(!table.part_table().entries[0].is_used());
+        assert_eq!(table.add_partition(linux_partition(34, 8191)), Ok(0));
+    }
+
+    #[test]
+    fn full_table() {
+        let mut table = GptBuilder::new(NUM_BLOCKS, LBS, PartUUID::from_u128(1))
+            .num_part_entries(2)
+            .build()
+            .unwrap();
+        table.add_partition(linux_partition(2048, 4095)).unwrap();
+        table.add_partition(linux_partition(4096, 8191)).unwrap();
+        let err = table
+            .add_partition(linux_partition(8192, 12287))
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::PartTableFull);
+    }
+
+    #[test]
+    fn allocate_partition() {
+        let mut table = table();
+        assert_eq!(table.allocate_partition(linux_partition(0, 0), 100), Ok(0));
+        // The next partition starts at the next MiB, the end of the first one is not rounded.
+        let blocks = Alignment::MIB.align_up(100, LBS).unwrap();
+        assert_eq!(
+            table.allocate_partition(linux_partition(0, 0), blocks),
+            Ok(1)
+        );
+        let ranges = table.part_table().entries[..2]
+            .iter()
+            .map(|entry| (entry.starting_lba, entry.ending_lba))
+            .collect::<Vec<_>>();
+        assert_eq!(ranges, [(2048, 2147), (4096, 6143)]);
+        assert_eq!(table.misaligned_partitions(Alignment::MIB), [0]);
+
+        let err = table
+            .allocate_partition(linux_partition(0, 0), 0)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::PartRange);
+        // 34..=2047 and 2148..=4095 are free too, but hold no LBA aligned to 1 MiB.
+        assert_eq!(table.allocate_partition(linux_partition(0, 0), 1000), Ok(2));
+        assert_eq!(table.part_table().entries[2].starting_lba, 6144);
+        table.set_alignment(Alignment::PHYSICAL_4K);
+        assert_eq!(table.allocate_partition(linux_partition(0, 0), 1000), Ok(3));
+        assert_eq!(table.part_table().entries[3].starting_lba, 40);
+        let err = table
+            .allocate_partition(linux_partition(0, 0), 16350)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::PartNoSpace);
+    }
+
+    #[test]
+    fn misaligned_partitions() {
+        let mut table = table();
+        table.add_partition(linux_partition(34, 2047)).unwrap();
+        table.add_partition(linux_partition(2048, 4095)).unwrap();
+        table.add_partition(linux_partition(4096, 4100)).unwrap();
+        table.add_partition(linux_partition(4104, 4111)).unwrap();
+        assert_eq!(table.misaligned_partitions(Alignment::MIB), [0, 2, 3]);
+        assert_eq!(table.misaligned_partitions(Alignment::PHYSICAL_4K), [0, 2]);
+        assert_eq!(table.misaligned_partitions(Alignment::new(512)), []);
+    }
+
+    /// The free regions of `table` as `(starting_lba, ending_lba)` pairs.
+    fn free_ranges(
+        table: &GuidPartTable,
+        alignment: Option<Alignment>,
+        min_blocks: u64,
+    ) -> Vec<(u64, u64)> {
+        table
+            .free_regions(alignment, min_blocks)
+            .iter()
+            .map(|region| (region.starting_lba, region.ending_lba))
+            .collect()
+    }
+
+    #[test]
+    fn free_regions() {
+        let mut table = table();
+        assert_eq!(free_ranges(&table, None, 0), [(34, 16350)]);
+        assert_eq!(free_ranges(&table, None, 16317), [(34, 16350)]);
+        assert_eq!(free_ranges(&table, None, 16318), []);
+
+        table.add_partition(linux_partition(2048, 4095)).unwrap();
+        table.add_partition(linux_partition(8192, 12287)).unwrap();
+        assert_eq!(
+            free_ranges(&table, None, 0),
+            [(34, 2047), (4096, 8191), (12288, 16350)]
+        );
+        assert_eq!(
+            free_ranges(&table, None, 2015),
+            [(4096, 8191), (12288, 16350)]
+        );
+        assert_eq!(free_ranges(&table, None, 4064), [(4096, 8191)]);
+        assert_eq!(free_ranges(&table, None, 4097), []);
+    }
+
+    #[test]
+    fn free_regions_at_usable_range_ends() {
+        let mut table = table();
+        table.add_partition(linux_partition(34, 2047)).unwrap();
+        table.add_partition(linux_partition(12288, 16350)).unwrap();
+        assert_eq!(free_ranges(&table, None, 0), [(2048, 12287)]);
+
+        table.add_partition(linux_partition(2048, 12287)).unwrap();
+        assert_eq!(free_ranges(&table, None, 0), []);
+    }
+
+    #[test]
+    fn free_regions_between_adjacent_partitions() {
+        let mut table = table();
+        table.add_partition(linux_partition(4096, 8191)).unwrap();
+        table.add_partition(linux_partition(2048, 4095)).unwrap();
+        table.add_partition(linux_partition(8192, 8192)).unwrap();
+        assert_eq!(free_ranges(&table, None, 0), [(34, 2047), (8193, 16350)]);
+    }
+
+    #[test]
+    fn free_regions_aligned() {
+        let mut table = table();
+        table.add_partition(linux_partition(2048, 4000)).unwrap();
+        table.add_partition(linux_partition(6000, 6200)).unwrap();
+        assert_eq!(
+            free_ranges(&table, Some(Alignment::PHYSICAL_4K), 0),
+            [(40, 2047), (4008, 5999), (6208, 16350)]
+        );
+        // 34..=2047 holds no LBA aligned to 1 MiB.
+        assert_eq!(
+            free_ranges(&table, Some(Alignment::MIB), 0),
+            [(4096, 5999), (8192, 16350)]
+        );
+        assert_eq!(
+            free_ranges(&table, Some(Alignment::MIB), 8159),
+            [(8192, 16350)]
+        );
+        assert_eq!(free_ranges(&table, Some(Alignment::MIB), 8160), []);
+    }
+}
@@ -1,7 +1,9 @@
-use crate::{Deserialize, GptError, LittleEndianBytes, PartUUID, Serialize};
-use alloc::vec::Vec;
+use crate::{
+    Deserialize, EntryIter, ErrorKind, GptError, LittleEndianBytes, LogicalBlockSize, PartUUID,
+    Serialize, check_buf, read_u32, read_u64,
+};
 
-// pub const PRIMARY_HEADER_LBA: usize = 1;
+pub(crate) const PRIMARY_HEADER_LBA: u64 = 1;
 
 const SIGNATURE: u64 = 0x5452415020494645;
 const SIGNATURE_OFFSET: usize = 0;
@@ -13,6 +15,7 @@ const REVISION_SIZE: usize = 4;
 
 const HDR_SIZE_OFFSET: usize = 12;
 const HDR_SIZE_SIZE: usize = 4;
+const HDR_SIZE: u32 = 92;
 
 const HDR_CRC32_OFFSET: usize = 16;
 const HDR_CRC32_SIZE: usize = 4;
@@ -38,7 +41,7 @@ const DISK_GUID_SIZE: usize = 16;
 const PARTITION_ENTRY_LBA_OFFSET: usize = 72;
 const PARTITION_ENTRY_LBA_SIZE: usize = 8;
 
-const NUMBER_OF_PARTITION_ENTRIES_OFFSET: usize = 80;
+pub(crate) const NUMBER_OF_PARTITION_ENTRIES_OFFSET: usize = 80;
 const NUMBER_OF_PARTITION_ENTRIES_SIZE: usize = 4;
 
 const SIZE_OF_PARTITION_ENTRY_OFFSET: usize = 84;
@@ -88,11 +91,139 @@ pub struct Header {
 }
 
 impl Header {
+    /// Create a header for `disk_guid` with all LBA fields and checksums set to zero.
+    pub fn new(disk_guid: PartUUID) -> Self {
+        Self {
+            signature: SIGNATURE.to_le_bytes(),
+            revision: REVISION,
+            header_size: HDR_SIZE,
+            header_crc32: 0,
+            reserved: 0,
+            my_lba: 0,
+            alternate_lba: 0,
+            first_usable_lba: 0,
+            last_usable_lba: 0,
+            disk_guid,
+            part_entry_lba: 0,
+            num_part_entries: 0,
+            part_entry_size: 0,
+            crc32_part_entry_array: 0,
+        }
+    }
+
+    /// Create the alternate of this header, whose partition entry array starts at `part_entry_lba`.
+    pub fn alternate(&self, part_entry_lba: u64) -> Self {
+        let mut header = Self {
+            my_lba: self.alternate_lba,
+            alternate_lba: self.my_lba,
+            part_entry_lba,
+            ..self.clone()
+        };
+        header.update_crc32();
+        header
+    }
+
+    /// Recompute `header_crc32` over the first `header_size` bytes of the header.
+    pub fn update_crc32(&mut self) {
+        let mut bytes = [0; HDR_SIZE as usize];
+        self.header_crc32 = 0;
+        self.serialize_into(&mut bytes)
+            .expect("buffer holds the whole header");
+        self.header_crc32 = crate::crc32_padded(&bytes, self.header_size as _);
+    }
+
+    /// Check the LBA fields of a header read from `lba` of a disk of `num_blocks` logical blocks.
+    ///
+    /// The primary header must be at LBA 1 and point to the backup header at the last LBA of the
+    /// disk, and vice versa. The usable area and the partition entry array must lie inside the
+    /// disk and must not overlap each other or the protective MBR and the headers.
+    pub fn validate(
+        &self,
+        lba: u64,
+        num_blocks: u64,
+        lbs: LogicalBlockSize,
+    ) -> Result<(), GptError> {
+        let last_lba = num_blocks
+            .checked_sub(1)
+            .ok_or(GptError::new(ErrorKind::DiskTooSmall))?;
+        if self.my_lba != lba {
+            return Err(GptError::new(ErrorKind::HdrMyLba)
+                .at(MYLBA_OFFSET)
+                .mismatch(lba, self.my_lba));
+        }
+
+        let reserved = [0, self.my_lba, self.alternate_lba];
+        let in_range = |lba: &u64, start: u64, end: u64| (start..=end).contains(lba);
+        if self.first_usable_lba > self.last_usable_lba
+            || self.last_usable_lba > last_lba
+            || reserved
+                .iter()
+                .any(|lba| in_range(lba, self.first_usable_lba, self.last_usable_lba))
+        {
+            return Err(GptError::new(ErrorKind::HdrUsableRange).at(FIRST_USABLE_LBA_OFFSET));
+        }
+
+        Self::check_part_entry_size(self.part_entry_size)?;
+        let array_blocks = (self.part_entry_array_size() as u64).div_ceil(lbs as u64);
+        if array_blocks > 0 {
+            let array_end = self
+                .part_entry_lba
+                .checked_add(array_blocks - 1)
+                .filter(|end| *end <= last_lba)
+                .ok_or(
+                    GptError::new(ErrorKind::HdrPartEntryArray).at(PARTITION_ENTRY_LBA_OFFSET),
+                )?;
+            if reserved
+                .iter()
+                .any(|lba| in_range(lba, self.part_entry_lba, array_end))
+                || (self.part_entry_lba <= self.last_usable_lba
+                    && self.first_usable_lba <= array_end)
+            {
+                return Err(
+                    GptError::new(ErrorKind::HdrPartEntryArray).at(PARTITION_ENTRY_LBA_OFFSET)
+                );
+            }
+        }
+
+        // Checked last, so that a header of a disk that grew fails with this error alone.
+        let alternate_lba = if lba == PRIMARY_HEADER_LBA {
+            last_lba
+        } else {
+            PRIMARY_HEADER_LBA
+        };
+        if self.alternate_lba != alternate_lba {
+            return Err(GptError::new(ErrorKind::HdrAlternateLba)
+                .at(ALTERNATE_LBA_OFFSET)
+                .mismatch(alternate_lba, self.alternate_lba));
+        }
+        Ok(())
+    }
+
+    /// Size in bytes of the partition entry array described by this header.
+    pub fn part_entry_array_size(&self) -> usize {
+        (self.num_part_entries as usize).saturating_mul(self.part_entry_size as usize)
+    }
+
+    /// Whether `other` is the alternate of this header, i.e. both headers describe the same
+    /// table and point at each other.
+    pub fn is_alternate_of(&self, other: &Header) -> bool {
+        self.my_lba == other.alternate_lba
+            && self.alternate_lba == other.my_lba
+            && self.first_usable_lba == other.first_usable_lba
+            && self.last_usable_lba == other.last_usable_lba
+            && self.disk_guid == other.disk_guid
+            && self.num_part_entries == other.num_part_entries
+            && self.part_entry_size == other.part_entry_size
+            && self.crc32_part_entry_array == other.crc32_part_entry_array
+    }
+
     fn check_signature(signature: u64) -> Result<(), GptError> {
         if signature == SIGNATURE {
             Ok(())
         } else {
-            Err(GptError::HdrSignature)
+            Err(GptError::new(ErrorKind::HdrSignature)
+                .at(SIGNATURE_OFFSET)
+                .mismatch(SIGNATURE, signature))
         }
     }
 
@@ -100,31 +231,48 @@ impl Header {
         if revision == REVISION {
             Ok(())
         } else {
-            Err(GptError::HdrRevision)
+            Err(GptError::new(ErrorKind::HdrRevision)
+                .at(REVISION_OFFSET)
+                .mismatch(REVISION as _, revision as _))
         }
     }
 
     fn check_header_size(header_size: u32, lba_size: u32) -> Result<(), GptError> {
-        if header_size >= 92 && header_size <= lba_size {
+        if header_size >= HDR_SIZE && header_size <= lba_size {
+            Ok(())
+        } else {
+            Err(GptError::new(ErrorKind::HdrSize).at(HDR_SIZE_OFFSET))
+        }
+    }
+
+    /// The size of a partition entry must be 128 x 2^n.
+    fn check_part_entry_size(part_entry_size: u32) -> Result<(), GptError> {
+        if part_entry_size >= 128 && part_entry_size.is_power_of_two() {
             Ok(())
         } else {
-            Err(GptError::HdrSize)
+            Err(GptError::new(ErrorKind::HdrPartEntrySize).at(SIZE_OF_PARTITION_ENTRY_OFFSET))
         }
     }
 
+    /// `bytes` is the header with its own CRC32 field, which is taken as zero.
     fn check_crc32(bytes: &[u8], crc32: u32) -> Result<(), GptError> {
-        let crc = crc::Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
-        if crc32 == crc.checksum(bytes) {
+        let checksum =
+            crate::crc32_zeroed(bytes, HDR_CRC32_OFFSET..HDR_CRC32_OFFSET + HDR_CRC32_SIZE);
+        if crc32 == checksum {
             Ok(())
         } else {
-            Err(GptError::HdrCrc32)
+            Err(GptError::new(ErrorKind::HdrCrc32)
+                .at(HDR_CRC32_OFFSET)
+                .mismatch(checksum as _, crc32 as _))
         }
     }
 }
 
 impl Serialize for Header {
-    fn serialize(&self, size: usize) -> Vec<u8> {
-        let mut bytes = vec![0; size];
+    const SIZE: usize = HDR_SIZE as _;
+
+    fn serialize_into(&self, bytes: &mut [u8]) -> Result<(), GptError> {
+        check_buf(bytes, Self::SIZE)?;
         bytes[SIGNATURE_OFFSET..SIGNATURE_OFFSET + SIGNATURE_SIZE].copy_from_slice(&self.signature);
         bytes[REVISION_OFFSET..REVISION_OFFSET + REVISION_SIZE]
             .copy_from_slice(&self.revision.to_le_bytes());
@@ -153,7 +301,7 @@ impl Serialize for Header {
         bytes[PARTITION_ENTRY_ARRAY_CRC32_OFFSET
             ..PARTITION_ENTRY_ARRAY_CRC32_OFFSET + PARTITION_ENTRY_ARRAY_CRC32_SIZE]
             .copy_from_slice(&self.crc32_part_entry_array.to_le_bytes());
-        bytes
+        Ok(())
     }
 }
 
@@ -161,33 +309,31 @@ impl Deserialize for Header {
     fn deserialize(data: &[u8]) -> Result<Self, GptError> {
         let mut ltbs = LittleEndianBytes::from(data);
 
-        let signature = ltbs.copy_from::<SIGNATURE_SIZE>(SIGNATURE_OFFSET);
-        Self::check_signature(ltbs.parse_u64().unwrap())?;
+        let signature = ltbs.copy_from::<SIGNATURE_SIZE>(SIGNATURE_OFFSET)?;
+        Self::check_signature(u64::from_le_bytes(signature))?;
 
-        let revision = ltbs.parse_u32().unwrap();
+        let revision = ltbs.parse_u32()?;
         Self::check_revision(revision)?;
 
-        let header_size = ltbs.parse_u32().unwrap();
-        Self::check_header_size(header_size, data.len() as _)?;
+        let header_size = ltbs.parse_u32()?;
+        Self::check_header_size(header_size, u32::try_from(data.len()).unwrap_or(u32::MAX))?;
 
-        let header_crc32 = ltbs.parse_u32().unwrap();
-        let mut bytes = ltbs[0..header_size as _].to_vec();
-        bytes[HDR_CRC32_OFFSET..HDR_CRC32_OFFSET + HDR_CRC32_SIZE]
-            .copy_from_slice(&[0; HDR_CRC32_SIZE]);
-        Self::check_crc32(&bytes, header_crc32)?;
+        let header_crc32 = ltbs.parse_u32()?;
+        Self::check_crc32(&ltbs[0..header_size as _], header_crc32)?;
 
-        let reserved = ltbs.parse_u32().unwrap();
-        let my_lba = ltbs.parse_u64().unwrap();
-        let alternate_lba = ltbs.parse_u64().unwrap();
-        let first_usable_lba = ltbs.parse_u64().unwrap();
-        let last_usable_lba = ltbs.parse_u64().unwrap();
+        let reserved = ltbs.parse_u32()?;
+        let my_lba = ltbs.parse_u64()?;
+        let alternate_lba = ltbs.parse_u64()?;
+        let first_usable_lba = ltbs.parse_u64()?;
+        let last_usable_lba = ltbs.parse_u64()?;
 
-        let disk_guid = PartUUID::try_from(&ltbs.copy_from::<DISK_GUID_SIZE>(DISK_GUID_OFFSET)[..])?;
+        let disk_guid =
+            PartUUID::try_from(&ltbs.copy_from::<DISK_GUID_SIZE>(DISK_GUID_OFFSET)?[..])?;
 
-        let part_entry_lba = ltbs.parse_u64().unwrap();
-        let num_part_entries = ltbs.parse_u32().unwrap();
-        let part_entry_size = ltbs.parse_u32().unwrap();
-        let crc32_part_entry_array = ltbs.parse_u32().unwrap();
+        let part_entry_lba = ltbs.parse_u64()?;
+        let num_part_entries = ltbs.parse_u32()?;
+        let part_entry_size = ltbs.parse_u32()?;
+        let crc32_part_entry_array = ltbs.parse_u32()?;
 
         Ok(Self {
             signature,
@@ -207,3 +353,301 @@ impl Deserialize for Header {
         })
     }
 }
+
+/// A GPT header borrowed from a buffer, whose fields are read without allocation.
+#[derive(Debug, Clone, Copy)]
+pub struct HeaderView<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> HeaderView<'a> {
+    /// Check the signature, revision, size and CRC32 of the header at the start of `data`,
+    /// which is usually one logical block.
+    pub fn new(data: &'a [u8]) -> Result<Self, GptError> {
+        if data.len() < HDR_SIZE as usize {
+            return Err(
+                GptError::new(ErrorKind::Truncated).mismatch(HDR_SIZE as _, data.len() as _)
+            );
+        }
+        Header::check_signature(read_u64(data, SIGNATURE_OFFSET))?;
+        Header::check_revision(read_u32(data, REVISION_OFFSET))?;
+        let header_size = read_u32(data, HDR_SIZE_OFFSET);
+        Header::check_header_size(header_size, u32::try_from(data.len()).unwrap_or(u32::MAX))?;
+        Header::check_crc32(
+            &data[..header_size as usize],
+            read_u32(data, HDR_CRC32_OFFSET),
+        )?;
+        Ok(Self { data })
+    }
+
+    pub fn revision(&self) -> u32 {
+        read_u32(self.data, REVISION_OFFSET)
+    }
+
+    pub fn header_size(&self) -> u32 {
+        read_u32(self.data, HDR_SIZE_OFFSET)
+    }
+
+    pub fn header_crc32(&self) -> u32 {
+        read_u32(self.data, HDR_CRC32_OFFSET)
+    }
+
+    pub fn my_lba(&self) -> u64 {
+        read_u64(self.data, MYLBA_OFFSET)
+    }
+
+    pub fn alternate_lba(&self) -> u64 {
+        read_u64(self.data, ALTERNATE_LBA_OFFSET)
+    }
+
+    pub fn first_usable_lba(&self) -> u64 {
+        read_u64(self.data, FIRST_USABLE_LBA_OFFSET)
+    }
+
+    pub fn last_usable_lba(&self) -> u64 {
+        read_u64(self.data, LAST_USABLE_LBA_OFFSET)
+    }
+
+    pub fn disk_guid(&self) -> PartUUID {
+        let mut guid = PartUUID::default();
+        guid.copy_from_slice(&self.data[DISK_GUID_OFFSET..DISK_GUID_OFFSET + DISK_GUID_SIZE]);
+        guid
+    }
+
+    pub fn part_entry_lba(&self) -> u64 {
+        read_u64(self.data, PARTITION_ENTRY_LBA_OFFSET)
+    }
+
+    pub fn num_part_entries(&self) -> u32 {
+        read_u32(self.data, NUMBER_OF_PARTITION_ENTRIES_OFFSET)
+    }
+
+    pub fn part_entry_size(&self) -> u32 {
+        read_u32(self.data, SIZE_OF_PARTITION_ENTRY_OFFSET)
+    }
+
+    pub fn crc32_part_entry_array(&self) -> u32 {
+        read_u32(self.data, PARTITION_ENTRY_ARRAY_CRC32_OFFSET)
+    }
+
+    /// Copy the fields into an owned [`Header`].
+    pub fn to_header(&self) -> Header {
+        Header {
+            signature: SIGNATURE.to_le_bytes(),
+            revision: self.revision(),
+            header_size: self.header_size(),
+            header_crc32: self.header_crc32(),
+            reserved: read_u32(self.data, RESERVED_OFFSET),
+            my_lba: self.my_lba(),
+            alternate_lba: self.alternate_lba(),
+            first_usable_lba: self.first_usable_lba(),
+            last_usable_lba: self.last_usable_lba(),
+            disk_guid: self.disk_guid(),
+            part_entry_lba: self.part_entry_lba(),
+            num_part_entries: self.num_part_entries(),
+            part_entry_size: self.part_entry_size(),
+            crc32_part_entry_array: self.crc32_part_entry_array(),
+        }
+    }
+
+    /// See [`Header::validate`].
+    pub fn validate(
+        &self,
+        lba: u64,
+        num_blocks: u64,
+        lbs: LogicalBlockSize,
+    ) -> Result<(), GptError> {
+        self.to_header().validate(lba, num_blocks, lbs)
+    }
+
+    /// Iterate over the partition entry array in `array` after checking its size and CRC32, and
+    /// that every used entry lies inside the usable area without overlapping another one.
+    pub fn entries<'b>(&self, array: &'b [u8]) -> Result<EntryIter<'b>, GptError> {
+        Header::check_part_entry_size(self.part_entry_size())?;
+        EntryIter::new(
+            array,
+            self.num_part_entries() as _,
+            self.part_entry_size() as _,
+            self.crc32_part_entry_array(),
+            self.first_usable_lba()..=self.last_usable_lba(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        PartEntry, PartTableEntry, Structure,
+        fixture::{LBS, NUM_BLOCKS, linux_partition, table},
+    };
+    use alloc::vec::Vec;
+
+    /// The primary header of an empty GPT on a disk of `NUM_BLOCKS` blocks, in one block.
+    fn header_block() -> Vec<u8> {
+        table().header().serialize(LBS as _)
+    }
+
+    #[test]
+    fn crc32_mismatch_reports_stored_value_as_found() {
+        let mut block = header_block();
+        let stored = read_u32(&block, HDR_CRC32_OFFSET);
+        block[HDR_CRC32_OFFSET..HDR_CRC32_OFFSET + HDR_CRC32_SIZE]
+            .copy_from_slice(&0xDEADBEEF_u32.to_le_bytes());
+
+        let err = Header::deserialize(&block).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::HdrCrc32);
+        assert_eq!(err.offset(), Some(HDR_CRC32_OFFSET as _));
+        assert_eq!(err.expected(), Some(stored as _));
+        assert_eq!(err.found(), Some(0xDEADBEEF));
+    }
+
+    fn validate(lba: u64, edit: impl FnOnce(&mut Header)) -> Result<(), GptError> {
+        let mut header = Header::deserialize(&header_block()).unwrap();
+        if lba != PRIMARY_HEADER_LBA {
+            header = header.alternate(NUM_BLOCKS - 33);
+        }
+        edit(&mut header);
+        header.validate(lba, NUM_BLOCKS, LBS)
+    }
+
+    fn kind(result: Result<(), GptError>) -> ErrorKind {
+        result.err().unwrap().kind()
+    }
+
+    #[test]
+    fn valid_headers() {
+        validate(PRIMARY_HEADER_LBA, |_| ()).unwrap();
+        validate(NUM_BLOCKS - 1, |_| ()).unwrap();
+    }
+
+    #[test]
+    fn my_lba() {
+        let err = validate(NUM_BLOCKS - 1, |header| header.my_lba = 2)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::HdrMyLba);
+        assert_eq!(err.offset(), Some(MYLBA_OFFSET as _));
+        assert_eq!(
+            (err.expected(), err.found()),
+            (Some(NUM_BLOCKS - 1), Some(2))
+        );
+    }
+
+    #[test]
+    fn alternate_lba() {
+        let err = validate(PRIMARY_HEADER_LBA, |header| header.alternate_lba = 16382)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::HdrAlternateLba);
+        assert_eq!(
+            (err.expected(), err.found()),
+            (Some(NUM_BLOCKS - 1), Some(16382))
+        );
+        assert_eq!(
+            kind(validate(NUM_BLOCKS - 1, |header| header.alternate_lba = 2)),
+            ErrorKind::HdrAlternateLba
+        );
+    }
+
+    #[test]
+    fn usable_range() {
+        for edit in [
+            |header: &mut Header| header.first_usable_lba = header.last_usable_lba + 1,
+            |header: &mut Header| header.last_usable_lba = NUM_BLOCKS,
+            |header: &mut Header| header.last_usable_lba = NUM_BLOCKS - 1,
+            |header: &mut Header| header.first_usable_lba = 0,
+            |header: &mut Header| header.first_usable_lba = 1,
+        ] {
+            assert_eq!(
+                kind(validate(PRIMARY_HEADER_LBA, edit)),
+                ErrorKind::HdrUsableRange
+            );
+        }
+    }
+
+    #[test]
+    fn part_entry_array() {
+        for edit in [
+            |header: &mut Header| header.part_entry_lba = 1,
+            |header: &mut Header| header.part_entry_lba = 3,
+            |header: &mut Header| header.part_entry_lba = NUM_BLOCKS - 32,
+            |header: &mut Header| header.part_entry_lba = u64::MAX,
+            |header: &mut Header| header.num_part_entries = 129,
+        ] {
+            assert_eq!(
+                kind(validate(PRIMARY_HEADER_LBA, edit)),
+                ErrorKind::HdrPartEntryArray
+            );
+        }
+        assert_eq!(
+            kind(validate(NUM_BLOCKS - 1, |header| header.part_entry_lba = 16350)),
+            ErrorKind::HdrPartEntryArray
+        );
+    }
+
+    #[test]
+    fn part_entry_size() {
+        for size in [0, 64, 127, 192, 384] {
+            assert_eq!(
+                kind(validate(PRIMARY_HEADER_LBA, |header| header
+                    .part_entry_size =
+                    size)),
+                ErrorKind::HdrPartEntrySize,
+                "{}",
+                size
+            );
+        }
+        validate(PRIMARY_HEADER_LBA, |header| {
+            header.part_entry_size = 256;
+            header.num_part_entries = 64;
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn truncated_header() {
+        let block = header_block();
+        for len in 0..HDR_SIZE as usize {
+            assert!(Header::deserialize(&block[..len]).is_err(), "{}", len);
+            assert!(HeaderView::new(&block[..len]).is_err(), "{}", len);
+        }
+        Header::deserialize(&block[..HDR_SIZE as usize]).unwrap();
+        HeaderView::new(&block[..HDR_SIZE as usize]).unwrap();
+    }
+
+    #[test]
+    fn header_with_huge_size_field() {
+        let mut header = table().header().clone();
+        header.header_size = u32::MAX;
+        let err = Header::deserialize(&header.serialize(LBS as _))
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::HdrSize);
+    }
+
+    #[test]
+    fn entries_checked_like_owned_table() {
+        let table = table();
+        let mut header = table.header().clone();
+        let mut entries = table.part_table().entries.clone();
+        entries[0] = linux_partition(2048, 4095);
+        entries[1] = linux_partition(4095, 8191);
+        let array = PartTableEntry { entries }.serialize_part_entries(128);
+        header.crc32_part_entry_array = crate::crc32(&array);
+        header.update_crc32();
+        let block = header.serialize(LBS as _);
+        let view = HeaderView::new(&block).unwrap();
+
+        let err = view.entries(&array).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::PartOverlap);
+        assert_eq!(err.structure(), Some(Structure::PartEntry(1)));
+        assert_eq!(Some(err), table.parse_part_table(&array, &header).err());
+
+        let mut array = array;
+        array[PartEntry::SIZE + 56] = b'x';
+        let err = view.entries(&array).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::PartEntryArrayCrc32);
+        assert_eq!(Some(err), table.parse_part_table(&array, &header).err());
+    }
+}
@@ -0,0 +1,238 @@
+use crate::{
+    ErrorKind, GptError, GuidPartTable, MbrPartRecord, PartitionType, ProtectiveMbr, Structure,
+    mbr::record_offset,
+};
+use alloc::vec::Vec;
+
+/// A hybrid MBR holds the 0xEE record and up to three mirrored partitions.
+const MAX_HYBRID_PARTS: usize = 3;
+
+/// A GPT partition to mirror into a hybrid MBR.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HybridPart {
+    /// Index of the partition in the partition entry array.
+    pub index: usize,
+    /// MBR partition type code, e.g. 0x0C for FAT32 or 0x83 for Linux.
+    pub os_type: u8,
+    /// Mark the record active with boot indicator 0x80.
+    pub active: bool,
+}
+
+/// How a record of a hybrid MBR maps back to the GPT.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HybridMapping {
+    /// Index of the MBR partition record.
+    pub record: usize,
+    /// Index of the GPT partition that starts at the same LBA, if any.
+    pub entry: Option<usize>,
+    /// Disagreements between the record and the GPT partition.
+    pub warnings: Vec<GptError>,
+}
+
+impl GuidPartTable {
+    /// Build a hybrid MBR that mirrors up to three partitions next to a 0xEE record.
+    ///
+    /// The 0xEE record comes first and covers the GPT header and entry array up to the first
+    /// mirrored partition. The boot code of the current MBR is kept.
+    pub fn hybrid_mbr(&self, parts: &[HybridPart]) -> Result<ProtectiveMbr, GptError> {
+        if parts.is_empty() {
+            return Err(GptError::new(ErrorKind::MbrHybridEmpty));
+        }
+        if parts.len() > MAX_HYBRID_PARTS {
+            return Err(GptError::new(ErrorKind::MbrHybridTooMany));
+        }
+        let entries = &self.part_table().entries;
+        let mut records = [MbrPartRecord::default(); MAX_HYBRID_PARTS + 1];
+        for (position, (record, part)) in records[1..].iter_mut().zip(parts).enumerate() {
+            let context = Structure::PartEntry(part.index as _);
+            let entry = entries
+                .get(part.index)
+                .filter(|entry| entry.is_used())
+                .ok_or(GptError::new(ErrorKind::PartIndex).inside(context, 0))?;
+            if parts[..position]
+                .iter()
+                .any(|other| other.index == part.index)
+            {
+                return Err(GptError::new(ErrorKind::MbrHybridDuplicate).inside(context, 0));
+            }
+            if entry.starting_lba > entry.ending_lba {
+                return Err(GptError::new(ErrorKind::PartRange).inside(context, 0));
+            }
+            if part.os_type == 0 || part.os_type == MbrPartRecord::PROTECTIVE_OS_TYPE {
+                return Err(GptError::new(ErrorKind::MbrPROsType).inside(context, 0));
+            }
+            let (Ok(starting_lba), Ok(size_in_lba)) = (
+                u32::try_from(entry.starting_lba),
+                u32::try_from(entry.num_blocks()),
+            ) else {
+                return Err(GptError::new(ErrorKind::MbrPROutOfRange).inside(context, 0));
+            };
+            *record = MbrPartRecord::new(part.os_type, starting_lba, size_in_lba)
+                .with_boot_indicator(part.active);
+        }
+        let first = records[1..=parts.len()]
+            .iter()
+            .map(MbrPartRecord::starting_lba)
+            .min()
+            .unwrap_or(1);
+        records[0] = MbrPartRecord::new(
+            MbrPartRecord::PROTECTIVE_OS_TYPE,
+            1,
+            first.saturating_sub(1),
+        );
+
+        let mut mbr = ProtectiveMbr::new(self.header().alternate_lba + 1)
+            .with_boot_code(self.mbr().boot_code);
+        mbr.part_records = records;
+        Ok(mbr)
+    }
+
+    /// Map the mirrored records of the current MBR back to the GPT partitions they start with.
+    ///
+    /// A record that matches no partition, covers a different range or has a type code that
+    /// maps to another partition type, see [`PartitionType::from_mbr_type`], is reported in the
+    /// warnings of its mapping. The result is empty for a plain protective MBR.
+    pub fn hybrid_mapping(&self) -> Vec<HybridMapping> {
+        let entries = &self.part_table().entries;
+        self.mbr()
+            .part_records
+            .iter()
+            .enumerate()
+            .filter(|(_, record)| !record.is_empty() && !record.is_protective())
+            .map(|(index, record)| {
+                let entry = entries.iter().position(|entry| {
+                    entry.is_used() && entry.starting_lba == record.starting_lba() as u64
+                });
+                let mut warnings = Vec::new();
+                match entry.map(|entry| &entries[entry]) {
+                    None => warnings.push(GptError::new(ErrorKind::MbrHybridUnmatched)),
+                    Some(entry) => {
+                        if entry.num_blocks() != record.size_in_lba() as u64 {
+                            warnings.push(
+                                GptError::new(ErrorKind::MbrPRSizeInLba)
+                                    .mismatch(entry.num_blocks(), record.size_in_lba() as _),
+                            );
+                        }
+                        if PartitionType::from_mbr_type(record.os_type())
+                            .is_some_and(|kind| kind.guid != entry.part_type_guid)
+                        {
+                            warnings.push(GptError::new(ErrorKind::MbrHybridType));
+                        }
+                    }
+                }
+                HybridMapping {
+                    record: index,
+                    entry,
+                    warnings: warnings
+                        .into_iter()
+                        .map(|err| err.at(record_offset(index)).inside(Structure::Mbr, 0))
+                        .collect(),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        PartTableEntry,
+        fixture::{self, LBS, linux_partition, partition},
+    };
+    use alloc::vec;
+
+    /// A table with an ESP at LBA 2048..=4095 and a Linux partition at LBA 4096..=8191.
+    fn table() -> GuidPartTable {
+        let mut table = fixture::table();
+        table
+            .add_partition(partition(PartitionType::EFI_SYSTEM, 2048, 4095))
+            .unwrap();
+        table.add_partition(linux_partition(4096, 8191)).unwrap();
+        table
+    }
+
+    fn part(index: usize, os_type: u8) -> HybridPart {
+        HybridPart {
+            index,
+            os_type,
+            active: false,
+        }
+    }
+
+    #[test]
+    fn build_and_map_hybrid_mbr() {
+        let mut table = table();
+        let mbr = table.hybrid_mbr(&[part(0, 0xEF), part(1, 0x83)]).unwrap();
+        assert!(mbr.part_records[0].is_protective());
+        assert_eq!(mbr.part_records[0].starting_lba(), 1);
+        assert_eq!(mbr.part_records[0].size_in_lba(), 2047);
+        assert_eq!(mbr.part_records[1], MbrPartRecord::new(0xEF, 2048, 2048));
+        assert_eq!(mbr.part_records[2], MbrPartRecord::new(0x83, 4096, 4096));
+
+        table.set_mbr(mbr);
+        let mapping = table.hybrid_mapping();
+        assert_eq!(mapping.len(), 2);
+        assert_eq!((mapping[0].record, mapping[0].entry), (1, Some(0)));
+        assert_eq!((mapping[1].record, mapping[1].entry), (2, Some(1)));
+        assert!(mapping.iter().all(|mapping| mapping.warnings.is_empty()));
+    }
+
+    #[test]
+    fn reject_partition_count() {
+        let table = table();
+        let err = table.hybrid_mbr(&[]).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::MbrHybridEmpty);
+        let parts = [part(0, 0xEF), part(1, 0x83), part(0, 0xEF), part(1, 0x83)];
+        let err = table.hybrid_mbr(&parts).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::MbrHybridTooMany);
+    }
+
+    #[test]
+    fn reject_duplicate_partition() {
+        let err = table()
+            .hybrid_mbr(&[part(1, 0x83), part(1, 0x83)])
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::MbrHybridDuplicate);
+        assert_eq!(err.structure(), Some(Structure::PartEntry(1)));
+    }
+
+    #[test]
+    fn reject_inverted_partition() {
+        let table = table();
+        let table = GuidPartTable::new(
+            LBS,
+            table.mbr().clone(),
+            table.header().clone(),
+            table.backup_header().clone(),
+            PartTableEntry {
+                entries: vec![linux_partition(4096, 2048)],
+            },
+        );
+        let err = table.hybrid_mbr(&[part(0, 0x83)]).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::PartRange);
+    }
+
+    #[test]
+    fn warn_about_disagreeing_records() {
+        let mut table = table();
+        let mut mbr = table.hybrid_mbr(&[part(0, 0xEF), part(1, 0x07)]).unwrap();
+        mbr.part_records[1] = MbrPartRecord::new(0xEF, 2048, 1024);
+        mbr.part_records[3] = MbrPartRecord::new(0x83, 10000, 100);
+        table.set_mbr(mbr);
+
+        let mapping = table.hybrid_mapping();
+        let kinds = |index: usize| {
+            mapping[index]
+                .warnings
+                .iter()
+                .map(GptError::kind)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(kinds(0), [ErrorKind::MbrPRSizeInLba]);
+        assert_eq!(kinds(1), [ErrorKind::MbrHybridType]);
+        assert_eq!(kinds(2), [ErrorKind::MbrHybridUnmatched]);
+        assert_eq!(mapping[1].warnings[0].offset(), Some(record_offset(2) as _));
+    }
+}
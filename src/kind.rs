@@ -0,0 +1,634 @@
+use crate::PartUUID;
+use core::fmt::Display;
+
+/// Operating system that a partition type belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionOs {
+    /// Defined by the UEFI specification or used by any operating system.
+    Uefi,
+    Linux,
+    Windows,
+    MacOs,
+    FreeBsd,
+    ChromeOs,
+    Android,
+}
+
+/// A well-known partition type GUID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartitionType {
+    pub guid: PartUUID,
+    /// Human-readable name, e.g. "EFI System".
+    pub name: &'static str,
+    pub os: PartitionOs,
+    /// Short names accepted by [`PartitionType::from_name`], e.g. "esp".
+    pub aliases: &'static [&'static str],
+}
+
+impl PartitionType {
+    pub const MBR_PARTITION_SCHEME: Self = Self::new(
+        0x024DEE41_33E7_11D3_9D69_0008C781F39F,
+        "MBR partition scheme",
+        PartitionOs::Uefi,
+        &[],
+    );
+    pub const EFI_SYSTEM: Self = Self::new(
+        0xC12A7328_F81F_11D2_BA4B_00A0C93EC93B,
+        "EFI System",
+        PartitionOs::Uefi,
+        &["esp", "efi"],
+    );
+    pub const BIOS_BOOT: Self = Self::new(
+        0x21686148_6449_6E6F_744E_656564454649,
+        "BIOS boot",
+        PartitionOs::Uefi,
+        &["bios", "bios_grub"],
+    );
+    pub const MICROSOFT_RESERVED: Self = Self::new(
+        0xE3C9E316_0B5C_4DB8_817D_F92DF00215AE,
+        "Microsoft reserved",
+        PartitionOs::Windows,
+        &["msr"],
+    );
+    pub const MICROSOFT_BASIC_DATA: Self = Self::new(
+        0xEBD0A0A2_B9E5_4433_87C0_68B6B72699C7,
+        "Microsoft basic data",
+        PartitionOs::Windows,
+        &["msdata", "basic"],
+    );
+    pub const WINDOWS_LDM_METADATA: Self = Self::new(
+        0x5808C8AA_7E8F_42E0_85D2_E1E90434CFB3,
+        "Windows LDM metadata",
+        PartitionOs::Windows,
+        &["ldm-metadata"],
+    );
+    pub const WINDOWS_LDM_DATA: Self = Self::new(
+        0xAF9B60A0_1431_4F62_BC68_3311714A69AD,
+        "Windows LDM data",
+        PartitionOs::Windows,
+        &["ldm-data"],
+    );
+    pub const WINDOWS_RECOVERY: Self = Self::new(
+        0xDE94BBA4_06D1_4D40_A16A_BFD50179D6AC,
+        "Windows recovery environment",
+        PartitionOs::Windows,
+        &["winre"],
+    );
+    pub const WINDOWS_STORAGE_SPACES: Self = Self::new(
+        0xE75CAF8F_F680_4CEE_AFA3_B001E56EFC2D,
+        "Windows Storage Spaces",
+        PartitionOs::Windows,
+        &["storage-spaces"],
+    );
+    pub const LINUX_FILESYSTEM: Self = Self::new(
+        0x0FC63DAF_8483_4772_8E79_3D69D8477DE4,
+        "Linux filesystem",
+        PartitionOs::Linux,
+        &["linux"],
+    );
+    pub const LINUX_RAID: Self = Self::new(
+        0xA19D880F_05FC_4D3B_A006_743F0F84911E,
+        "Linux RAID",
+        PartitionOs::Linux,
+        &["raid"],
+    );
+    pub const LINUX_ROOT_X86: Self = Self::new(
+        0x44479540_F297_41B2_9AF7_D131D5F0458A,
+        "Linux root (x86)",
+        PartitionOs::Linux,
+        &["root-x86"],
+    );
+    pub const LINUX_ROOT_X86_64: Self = Self::new(
+        0x4F68BCE3_E8CD_4DB1_96E7_FBCAF984B709,
+        "Linux root (x86-64)",
+        PartitionOs::Linux,
+        &["root-x86-64"],
+    );
+    pub const LINUX_ROOT_ARM: Self = Self::new(
+        0x69DAD710_2CE4_4E3C_B16C_21A1D49ABED3,
+        "Linux root (ARM)",
+        PartitionOs::Linux,
+        &["root-arm"],
+    );
+    pub const LINUX_ROOT_ARM64: Self = Self::new(
+        0xB921B045_1DF0_41C3_AF44_4C6F280D3FAE,
+        "Linux root (ARM64)",
+        PartitionOs::Linux,
+        &["root-arm64"],
+    );
+    pub const LINUX_ROOT_RISCV64: Self = Self::new(
+        0x72EC70A6_CF74_40E6_BD49_4BDA08E8F224,
+        "Linux root (RISC-V 64)",
+        PartitionOs::Linux,
+        &["root-riscv64"],
+    );
+    pub const LINUX_USR_X86_64: Self = Self::new(
+        0x8484680C_9521_48C6_9C11_B0720656F69E,
+        "Linux /usr (x86-64)",
+        PartitionOs::Linux,
+        &["usr-x86-64"],
+    );
+    pub const LINUX_USR_ARM64: Self = Self::new(
+        0xB0E01050_EE5F_4390_949A_9101B17104E9,
+        "Linux /usr (ARM64)",
+        PartitionOs::Linux,
+        &["usr-arm64"],
+    );
+    pub const LINUX_SWAP: Self = Self::new(
+        0x0657FD6D_A4AB_43C4_84E5_0933C84B4F4F,
+        "Linux swap",
+        PartitionOs::Linux,
+        &["swap"],
+    );
+    pub const LINUX_LVM: Self = Self::new(
+        0xE6D6D379_F507_44C2_A23C_238F2A3DF928,
+        "Linux LVM",
+        PartitionOs::Linux,
+        &["lvm"],
+    );
+    pub const LINUX_HOME: Self = Self::new(
+        0x933AC7E1_2EB4_4F13_B844_0E14E2AEF915,
+        "Linux /home",
+        PartitionOs::Linux,
+        &["home"],
+    );
+    pub const LINUX_SRV: Self = Self::new(
+        0x3B8F8425_20E0_4F3B_907F_1A25A76F98E8,
+        "Linux /srv",
+        PartitionOs::Linux,
+        &["srv"],
+    );
+    pub const LINUX_VAR: Self = Self::new(
+        0x4D21B016_B534_45C2_A9FB_5C16E091FD2D,
+        "Linux /var",
+        PartitionOs::Linux,
+        &["var"],
+    );
+    pub const LINUX_VAR_TMP: Self = Self::new(
+        0x7EC6F557_3BC5_4ACA_B293_16EF5DF639D1,
+        "Linux /var/tmp",
+        PartitionOs::Linux,
+        &["var-tmp"],
+    );
+    pub const LINUX_EXTENDED_BOOT: Self = Self::new(
+        0xBC13C2FF_59E6_4262_A352_B275FD6F7172,
+        "Linux extended boot",
+        PartitionOs::Linux,
+        &["xbootldr"],
+    );
+    pub const LINUX_DM_CRYPT: Self = Self::new(
+        0x7FFEC5C9_2D00_49B7_8941_3EA10A5586B7,
+        "Linux dm-crypt",
+        PartitionOs::Linux,
+        &["dm-crypt"],
+    );
+    pub const LINUX_LUKS: Self = Self::new(
+        0xCA7D7CCB_63ED_4C53_861C_1742536059CC,
+        "Linux LUKS",
+        PartitionOs::Linux,
+        &["luks"],
+    );
+    pub const LINUX_RESERVED: Self = Self::new(
+        0x8DA63339_0007_60C0_C436_083AC8230908,
+        "Linux reserved",
+        PartitionOs::Linux,
+        &[],
+    );
+    pub const APPLE_HFS_PLUS: Self = Self::new(
+        0x48465300_0000_11AA_AA11_00306543ECAC,
+        "Apple HFS+",
+        PartitionOs::MacOs,
+        &["hfs+", "hfs"],
+    );
+    pub const APPLE_APFS: Self = Self::new(
+        0x7C3457EF_0000_11AA_AA11_00306543ECAC,
+        "Apple APFS",
+        PartitionOs::MacOs,
+        &["apfs"],
+    );
+    pub const APPLE_UFS: Self = Self::new(
+        0x55465300_0000_11AA_AA11_00306543ECAC,
+        "Apple UFS",
+        PartitionOs::MacOs,
+        &[],
+    );
+    pub const APPLE_RAID: Self = Self::new(
+        0x52414944_0000_11AA_AA11_00306543ECAC,
+        "Apple RAID",
+        PartitionOs::MacOs,
+        &[],
+    );
+    pub const APPLE_RAID_OFFLINE: Self = Self::new(
+        0x52414944_5F4F_11AA_AA11_00306543ECAC,
+        "Apple RAID offline",
+        PartitionOs::MacOs,
+        &[],
+    );
+    pub const APPLE_BOOT: Self = Self::new(
+        0x426F6F74_0000_11AA_AA11_00306543ECAC,
+        "Apple boot",
+        PartitionOs::MacOs,
+        &["apple-recovery"],
+    );
+    pub const APPLE_LABEL: Self = Self::new(
+        0x4C616265_6C00_11AA_AA11_00306543ECAC,
+        "Apple label",
+        PartitionOs::MacOs,
+        &[],
+    );
+    pub const APPLE_CORE_STORAGE: Self = Self::new(
+        0x53746F72_6167_11AA_AA11_00306543ECAC,
+        "Apple Core Storage",
+        PartitionOs::MacOs,
+        &[],
+    );
+    pub const FREEBSD_BOOT: Self = Self::new(
+        0x83BD6B9D_7F41_11DC_BE0B_001560B84F0F,
+        "FreeBSD boot",
+        PartitionOs::FreeBsd,
+        &[],
+    );
+    pub const FREEBSD_DATA: Self = Self::new(
+        0x516E7CB4_6ECF_11D6_8FF8_00022D09712B,
+        "FreeBSD data",
+        PartitionOs::FreeBsd,
+        &[],
+    );
+    pub const FREEBSD_SWAP: Self = Self::new(
+        0x516E7CB5_6ECF_11D6_8FF8_00022D09712B,
+        "FreeBSD swap",
+        PartitionOs::FreeBsd,
+        &["freebsd-swap"],
+    );
+    pub const FREEBSD_UFS: Self = Self::new(
+        0x516E7CB6_6ECF_11D6_8FF8_00022D09712B,
+        "FreeBSD UFS",
+        PartitionOs::FreeBsd,
+        &["freebsd-ufs"],
+    );
+    pub const FREEBSD_VINUM: Self = Self::new(
+        0x516E7CB8_6ECF_11D6_8FF8_00022D09712B,
+        "FreeBSD Vinum",
+        PartitionOs::FreeBsd,
+        &["freebsd-vinum"],
+    );
+    pub const FREEBSD_ZFS: Self = Self::new(
+        0x516E7CBA_6ECF_11D6_8FF8_00022D09712B,
+        "FreeBSD ZFS",
+        PartitionOs::FreeBsd,
+        &["freebsd-zfs"],
+    );
+    pub const CHROMEOS_KERNEL: Self = Self::new(
+        0xFE3A2A5D_4F32_41A7_B725_ACCC3285A309,
+        "ChromeOS kernel",
+        PartitionOs::ChromeOs,
+        &[],
+    );
+    pub const CHROMEOS_ROOT: Self = Self::new(
+        0x3CB8E202_3B7E_47DD_8A3C_7FF2A13CFCEC,
+        "ChromeOS root",
+        PartitionOs::ChromeOs,
+        &[],
+    );
+    pub const CHROMEOS_FIRMWARE: Self = Self::new(
+        0xCAB6E88E_ABF3_4102_A07A_D4BB9BE3C1D3,
+        "ChromeOS firmware",
+        PartitionOs::ChromeOs,
+        &[],
+    );
+    pub const CHROMEOS_RESERVED: Self = Self::new(
+        0x2E0A753D_9E48_43B0_8337_B15192CB1B5E,
+        "ChromeOS reserved",
+        PartitionOs::ChromeOs,
+        &[],
+    );
+    pub const CHROMEOS_MINIOS: Self = Self::new(
+        0x09845860_705F_4BB5_B16C_8A8A099CAF52,
+        "ChromeOS miniOS",
+        PartitionOs::ChromeOs,
+        &[],
+    );
+    pub const CHROMEOS_HIBERNATE: Self = Self::new(
+        0x3F0F8318_F146_4E6B_8222_C28C8F02E0D5,
+        "ChromeOS hibernate",
+        PartitionOs::ChromeOs,
+        &[],
+    );
+    pub const ANDROID_BOOTLOADER: Self = Self::new(
+        0x2568845D_2332_4675_BC39_8FA5A4748D15,
+        "Android bootloader",
+        PartitionOs::Android,
+        &[],
+    );
+    pub const ANDROID_BOOTLOADER2: Self = Self::new(
+        0x114EAFFE_1552_4022_B26E_9B053604CF84,
+        "Android bootloader 2",
+        PartitionOs::Android,
+        &[],
+    );
+    pub const ANDROID_BOOT: Self = Self::new(
+        0x49A4D17F_93A3_45C1_A0DE_F50B2EBE2599,
+        "Android boot",
+        PartitionOs::Android,
+        &[],
+    );
+    pub const ANDROID_RECOVERY: Self = Self::new(
+        0x4177C722_9E92_4AAB_8644_43502BFD5506,
+        "Android recovery",
+        PartitionOs::Android,
+        &[],
+    );
+    pub const ANDROID_MISC: Self = Self::new(
+        0xEF32A33B_A409_486C_9141_9FFB711F6266,
+        "Android misc",
+        PartitionOs::Android,
+        &[],
+    );
+    pub const ANDROID_METADATA: Self = Self::new(
+        0x20AC26BE_20B7_11E3_84C5_6CFDB94711E9,
+        "Android metadata",
+        PartitionOs::Android,
+        &[],
+    );
+    pub const ANDROID_SYSTEM: Self = Self::new(
+        0x38F428E6_D326_425D_9140_6E0EA133647C,
+        "Android system",
+        PartitionOs::Android,
+        &[],
+    );
+    pub const ANDROID_CACHE: Self = Self::new(
+        0xA893EF21_E428_470A_9E55_0668FD91A2D9,
+        "Android cache",
+        PartitionOs::Android,
+        &[],
+    );
+    pub const ANDROID_DATA: Self = Self::new(
+        0xDC76DDA9_5AC1_491C_AF42_A82591580C0D,
+        "Android data",
+        PartitionOs::Android,
+        &[],
+    );
+    pub const ANDROID_PERSISTENT: Self = Self::new(
+        0xEBC597D0_2053_4B15_8B64_E0AAC75F4DB1,
+        "Android persistent",
+        PartitionOs::Android,
+        &[],
+    );
+    pub const ANDROID_VENDOR: Self = Self::new(
+        0xC5A0AEEC_13EA_11E5_A1B1_001E67CA0C3C,
+        "Android vendor",
+        PartitionOs::Android,
+        &[],
+    );
+    pub const ANDROID_CONFIG: Self = Self::new(
+        0xBD59408B_4514_490D_BF12_9878D963F378,
+        "Android config",
+        PartitionOs::Android,
+        &[],
+    );
+    pub const ANDROID_FACTORY: Self = Self::new(
+        0x8F68CC74_C5E5_48DA_BE91_A0C8C15E9C80,
+        "Android factory",
+        PartitionOs::Android,
+        &[],
+    );
+    pub const ANDROID_FACTORY_ALT: Self = Self::new(
+        0x9FDAA6EF_4B3F_40D2_BA8D_BFF16BFB887B,
+        "Android factory (alternate)",
+        PartitionOs::Android,
+        &[],
+    );
+    pub const ANDROID_FASTBOOT: Self = Self::new(
+        0x767941D0_2085_11E3_AD3B_6CFDB94711E9,
+        "Android fastboot",
+        PartitionOs::Android,
+        &[],
+    );
+    pub const ANDROID_OEM: Self = Self::new(
+        0xAC6D7924_EB71_4DF8_B48D_E267B27148FF,
+        "Android OEM",
+        PartitionOs::Android,
+        &[],
+    );
+
+    const fn new(
+        guid: u128,
+        name: &'static str,
+        os: PartitionOs,
+        aliases: &'static [&'static str],
+    ) -> Self {
+        Self {
+            guid: PartUUID::from_u128(guid),
+            name,
+            os,
+            aliases,
+        }
+    }
+
+    /// All partition types of the catalogue.
+    pub fn all() -> &'static [PartitionType] {
+        PARTITION_TYPES
+    }
+
+    pub fn from_guid(guid: &PartUUID) -> Option<Self> {
+        PARTITION_TYPES.iter().find(|ty| ty.guid.eq(guid)).copied()
+    }
+
+    /// Look up a partition type by its name or one of its aliases, ignoring ASCII case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        PARTITION_TYPES
+            .iter()
+            .find(|ty| {
+                ty.name.eq_ignore_ascii_case(name)
+                    || ty
+                        .aliases
+                        .iter()
+                        .any(|alias| alias.eq_ignore_ascii_case(name))
+            })
+            .copied()
+    }
+
+    /// The partition type that an MBR partition type code maps to when converting to GPT, e.g.
+    /// 0x83 to Linux filesystem. Extended and unknown codes have no mapping.
+    pub fn from_mbr_type(os_type: u8) -> Option<Self> {
+        MBR_TYPES
+            .iter()
+            .find(|(code, _)| *code == os_type)
+            .map(|(_, ty)| *ty)
+    }
+}
+
+impl Display for PartitionType {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.name)
+    }
+}
+
+/// MBR partition type codes and the partition types they map to, as gdisk converts them.
+const MBR_TYPES: &[(u8, PartitionType)] = &[
+    (0x01, PartitionType::MICROSOFT_BASIC_DATA),
+    (0x04, PartitionType::MICROSOFT_BASIC_DATA),
+    (0x06, PartitionType::MICROSOFT_BASIC_DATA),
+    (0x07, PartitionType::MICROSOFT_BASIC_DATA),
+    (0x0B, PartitionType::MICROSOFT_BASIC_DATA),
+    (0x0C, PartitionType::MICROSOFT_BASIC_DATA),
+    (0x0E, PartitionType::MICROSOFT_BASIC_DATA),
+    (0x11, PartitionType::MICROSOFT_BASIC_DATA),
+    (0x14, PartitionType::MICROSOFT_BASIC_DATA),
+    (0x16, PartitionType::MICROSOFT_BASIC_DATA),
+    (0x17, PartitionType::MICROSOFT_BASIC_DATA),
+    (0x1B, PartitionType::MICROSOFT_BASIC_DATA),
+    (0x1C, PartitionType::MICROSOFT_BASIC_DATA),
+    (0x1E, PartitionType::MICROSOFT_BASIC_DATA),
+    (0x27, PartitionType::WINDOWS_RECOVERY),
+    (0x42, PartitionType::WINDOWS_LDM_DATA),
+    (0x82, PartitionType::LINUX_SWAP),
+    (0x83, PartitionType::LINUX_FILESYSTEM),
+    (0x8E, PartitionType::LINUX_LVM),
+    (0xA5, PartitionType::FREEBSD_DATA),
+    (0xA8, PartitionType::APPLE_UFS),
+    (0xAB, PartitionType::APPLE_BOOT),
+    (0xAF, PartitionType::APPLE_HFS_PLUS),
+    (0xEF, PartitionType::EFI_SYSTEM),
+    (0xFD, PartitionType::LINUX_RAID),
+];
+
+const PARTITION_TYPES: &[PartitionType] = &[
+    PartitionType::MBR_PARTITION_SCHEME,
+    PartitionType::EFI_SYSTEM,
+    PartitionType::BIOS_BOOT,
+    PartitionType::MICROSOFT_RESERVED,
+    PartitionType::MICROSOFT_BASIC_DATA,
+    PartitionType::WINDOWS_LDM_METADATA,
+    PartitionType::WINDOWS_LDM_DATA,
+    PartitionType::WINDOWS_RECOVERY,
+    PartitionType::WINDOWS_STORAGE_SPACES,
+    PartitionType::LINUX_FILESYSTEM,
+    PartitionType::LINUX_RAID,
+    PartitionType::LINUX_ROOT_X86,
+    PartitionType::LINUX_ROOT_X86_64,
+    PartitionType::LINUX_ROOT_ARM,
+    PartitionType::LINUX_ROOT_ARM64,
+    PartitionType::LINUX_ROOT_RISCV64,
+    PartitionType::LINUX_USR_X86_64,
+    PartitionType::LINUX_USR_ARM64,
+    PartitionType::LINUX_SWAP,
+    PartitionType::LINUX_LVM,
+    PartitionType::LINUX_HOME,
+    PartitionType::LINUX_SRV,
+    PartitionType::LINUX_VAR,
+    PartitionType::LINUX_VAR_TMP,
+    PartitionType::LINUX_EXTENDED_BOOT,
+    PartitionType::LINUX_DM_CRYPT,
+    PartitionType::LINUX_LUKS,
+    PartitionType::LINUX_RESERVED,
+    PartitionType::APPLE_HFS_PLUS,
+    PartitionType::APPLE_APFS,
+    PartitionType::APPLE_UFS,
+    PartitionType::APPLE_RAID,
+    PartitionType::APPLE_RAID_OFFLINE,
+    PartitionType::APPLE_BOOT,
+    PartitionType::APPLE_LABEL,
+    PartitionType::APPLE_CORE_STORAGE,
+    PartitionType::FREEBSD_BOOT,
+    PartitionType::FREEBSD_DATA,
+    PartitionType::FREEBSD_SWAP,
+    PartitionType::FREEBSD_UFS,
+    PartitionType::FREEBSD_VINUM,
+    PartitionType::FREEBSD_ZFS,
+    PartitionType::CHROMEOS_KERNEL,
+    PartitionType::CHROMEOS_ROOT,
+    PartitionType::CHROMEOS_FIRMWARE,
+    PartitionType::CHROMEOS_RESERVED,
+    PartitionType::CHROMEOS_MINIOS,
+    PartitionType::CHROMEOS_HIBERNATE,
+    PartitionType::ANDROID_BOOTLOADER,
+    PartitionType::ANDROID_BOOTLOADER2,
+    PartitionType::ANDROID_BOOT,
+    PartitionType::ANDROID_RECOVERY,
+    PartitionType::ANDROID_MISC,
+    PartitionType::ANDROID_METADATA,
+    PartitionType::ANDROID_SYSTEM,
+    PartitionType::ANDROID_CACHE,
+    PartitionType::ANDROID_DATA,
+    PartitionType::ANDROID_PERSISTENT,
+    PartitionType::ANDROID_VENDOR,
+    PartitionType::ANDROID_CONFIG,
+    PartitionType::ANDROID_FACTORY,
+    PartitionType::ANDROID_FACTORY_ALT,
+    PartitionType::ANDROID_FASTBOOT,
+    PartitionType::ANDROID_OEM,
+];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloc::{string::String, vec::Vec};
+
+    #[test]
+    fn unique_guids() {
+        for (index, ty) in PARTITION_TYPES.iter().enumerate() {
+            assert!(
+                PARTITION_TYPES[index + 1..]
+                    .iter()
+                    .all(|other| other.guid != ty.guid),
+                "{} GUID is not unique",
+                ty
+            );
+        }
+    }
+
+    #[test]
+    fn unique_names_and_aliases() {
+        let mut names = PARTITION_TYPES
+            .iter()
+            .flat_map(|ty| core::iter::once(&ty.name).chain(ty.aliases))
+            .map(|name| name.to_ascii_lowercase())
+            .collect::<Vec<String>>();
+        let count = names.len();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), count);
+    }
+
+    #[test]
+    fn lookup_round_trip() {
+        for ty in PARTITION_TYPES {
+            assert_eq!(PartitionType::from_guid(&ty.guid), Some(*ty));
+            assert_eq!(PartitionType::from_name(ty.name), Some(*ty));
+            assert_eq!(
+                PartitionType::from_name(&ty.name.to_ascii_uppercase()),
+                Some(*ty)
+            );
+            for alias in ty.aliases {
+                assert_eq!(PartitionType::from_name(alias), Some(*ty));
+                assert_eq!(
+                    PartitionType::from_name(&alias.to_ascii_uppercase()),
+                    Some(*ty)
+                );
+            }
+        }
+        assert_eq!(
+            PartitionType::from_name("ESP"),
+            Some(PartitionType::EFI_SYSTEM)
+        );
+        assert_eq!(PartitionType::from_name("no such type"), None);
+        assert_eq!(PartitionType::from_guid(&PartUUID::default()), None);
+    }
+
+    #[test]
+    fn mbr_types() {
+        for (code, ty) in MBR_TYPES {
+            assert_eq!(PartitionType::from_mbr_type(*code), Some(*ty));
+            assert_eq!(PartitionType::from_guid(&ty.guid), Some(*ty));
+        }
+        assert_eq!(
+            PartitionType::from_mbr_type(0x83),
+            Some(PartitionType::LINUX_FILESYSTEM)
+        );
+        // Protective, extended and empty records have no GPT partition type.
+        for code in [0x00, 0x05, 0x0F, 0x85, 0xEE] {
+            assert_eq!(PartitionType::from_mbr_type(code), None);
+        }
+    }
+}
@@ -0,0 +1,530 @@
+use crate::{
+    BlockDevice, ErrorKind, GptBuilder, GptError, GuidPartTable, GuidRng, MbrPartRecord, PartEntry,
+    PartName, PartUUID, PartitionType, Structure,
+    mbr::{check_sector_signature, read_records, record_offset},
+    read_u32,
+};
+use alloc::{vec, vec::Vec};
+
+const DISK_SIGNATURE_OFFSET: usize = 440;
+/// Linux numbers the logical partitions from 5, after the four primary records.
+const FIRST_LOGICAL_NUMBER: u32 = 5;
+/// Upper bound on the EBRs of a chain, which also stops chains that are too long to be real.
+const MAX_LOGICAL_PARTS: usize = 256;
+const ACTIVE: u8 = 0x80;
+
+/// Where a partition of a legacy MBR is recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LegacyPartKind {
+    /// One of the four records of the MBR.
+    Primary,
+    /// A primary record that holds the EBR chain of the logical partitions.
+    Extended,
+    /// The first record of an EBR.
+    Logical,
+}
+
+/// A partition of a legacy MBR.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LegacyPart {
+    /// Partition number as used by Linux, 1 to 4 for primary records and 5 onwards for logical
+    /// partitions.
+    pub number: u32,
+    pub kind: LegacyPartKind,
+    /// The record as stored on disk. The starting LBA of a logical partition is relative to its
+    /// EBR.
+    pub record: MbrPartRecord,
+    /// Absolute LBA of the first block of the partition.
+    pub starting_lba: u64,
+    /// LBA of the MBR or EBR that holds the record.
+    pub table_lba: u64,
+}
+
+impl LegacyPart {
+    /// Partition type code, e.g. 0x83 for Linux.
+    pub fn os_type(&self) -> u8 {
+        self.record.os_type()
+    }
+
+    /// Whether the boot indicator is 0x80.
+    pub fn is_bootable(&self) -> bool {
+        self.record.boot_indicator() == ACTIVE
+    }
+
+    pub fn starting_chs(&self) -> [u8; 3] {
+        self.record.starting_chs()
+    }
+
+    pub fn ending_chs(&self) -> [u8; 3] {
+        self.record.ending_chs()
+    }
+
+    pub fn num_blocks(&self) -> u64 {
+        self.record.size_in_lba() as u64
+    }
+
+    /// Absolute LBA of the last block of the partition.
+    pub fn ending_lba(&self) -> u64 {
+        self.starting_lba + self.num_blocks() - 1
+    }
+
+    /// The GPT partition type of the type code. Codes without a mapping become Linux
+    /// filesystem, as gdisk does.
+    pub fn partition_type(&self) -> PartitionType {
+        PartitionType::from_mbr_type(self.os_type()).unwrap_or(PartitionType::LINUX_FILESYSTEM)
+    }
+
+    /// Add the location of the record to `err`.
+    fn context(&self, err: GptError) -> GptError {
+        let (structure, index) = match self.kind {
+            LegacyPartKind::Logical => (Structure::Ebr, 0),
+            _ => (Structure::Mbr, self.number as usize - 1),
+        };
+        err.at(record_offset(index))
+            .inside(structure, 0)
+            .read_from(self.table_lba)
+    }
+}
+
+/// A legacy MBR partition table with the logical partitions of its extended partition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LegacyMbr {
+    pub disk_signature: u32,
+    /// The used primary records and logical partitions, in on-disk order.
+    pub parts: Vec<LegacyPart>,
+}
+
+impl LegacyMbr {
+    /// Read the MBR at LBA 0 of `dev` and follow the EBR chain of the first extended partition.
+    ///
+    /// Every partition must lie on the disk and every logical partition inside the extended
+    /// partition. A chain that visits an EBR twice or holds more than 256 logical partitions is
+    /// rejected.
+    pub fn read<D: BlockDevice + ?Sized>(dev: &D) -> Result<Self, GptError> {
+        let mut sector = vec![0; dev.block_size() as usize];
+        dev.read_blocks(0, &mut sector)
+            .and_then(|_| check_sector_signature(&sector))
+            .map_err(|err| err.inside(Structure::Mbr, 0).read_from(0))?;
+
+        let disk_signature = read_u32(&sector, DISK_SIGNATURE_OFFSET);
+        let mut parts = Vec::new();
+        for (index, record) in read_records(&sector).into_iter().enumerate() {
+            if !Self::is_used(&record) {
+                continue;
+            }
+            let part = LegacyPart {
+                number: index as u32 + 1,
+                kind: if record.is_extended() {
+                    LegacyPartKind::Extended
+                } else {
+                    LegacyPartKind::Primary
+                },
+                record,
+                starting_lba: record.starting_lba() as _,
+                table_lba: 0,
+            };
+            if part.starting_lba == 0 || part.ending_lba() >= dev.num_blocks() {
+                return Err(GptError::new(ErrorKind::MbrPROutOfRange)
+                    .at(record_offset(index))
+                    .inside(Structure::Mbr, 0)
+                    .read_from(0));
+            }
+            parts.push(part);
+        }
+        let extended = parts
+            .iter()
+            .find(|part| part.kind == LegacyPartKind::Extended)
+            .copied();
+        if let Some(extended) = extended {
+            Self::read_logicals(dev, &extended, &mut sector, &mut parts)?;
+        }
+        Ok(Self {
+            disk_signature,
+            parts,
+        })
+    }
+
+    /// The partitions that hold data, i.e. all but the extended partition.
+    pub fn partitions(&self) -> impl Iterator<Item = &LegacyPart> + '_ {
+        self.parts
+            .iter()
+            .filter(|part| part.kind != LegacyPartKind::Extended)
+    }
+
+    /// Convert the table to a GPT built by `builder` with a random GUID for every partition.
+    #[cfg(feature = "std")]
+    pub fn to_gpt(&self, builder: GptBuilder) -> Result<GuidPartTable, GptError> {
+        self.to_gpt_with(builder, &mut crate::OsRng)
+    }
+
+    /// Convert the table to a GPT built by `builder`, taking the partition GUIDs from `rng`.
+    ///
+    /// Every data partition keeps its range and gets the type of
+    /// [`LegacyPart::partition_type`], named after it, in the order of [`LegacyMbr::partitions`].
+    /// Bootable partitions are marked legacy BIOS bootable. The table gets the protective MBR of
+    /// the builder, and the disk is left untouched until the table is written.
+    ///
+    /// A partition that overlaps the protective MBR, the GPT headers or the partition entry
+    /// arrays fails with [`ErrorKind::MbrGptOverlap`] and has to be shrunk or moved first. The
+    /// entry arrays take at least 16 KiB, so with 512-byte blocks the partitions must start at
+    /// LBA 34 or later and leave the last 33 blocks of the disk free, with 4096-byte blocks
+    /// LBA 6 and the last 5 blocks.
+    pub fn to_gpt_with<R: GuidRng + ?Sized>(
+        &self,
+        builder: GptBuilder,
+        rng: &mut R,
+    ) -> Result<GuidPartTable, GptError> {
+        let mut table = builder.build()?;
+        let (first_usable_lba, last_usable_lba) = (
+            table.header().first_usable_lba,
+            table.header().last_usable_lba,
+        );
+        for part in self.partitions() {
+            if part.starting_lba < first_usable_lba {
+                return Err(part.context(
+                    GptError::new(ErrorKind::MbrGptOverlap)
+                        .mismatch(first_usable_lba, part.starting_lba),
+                ));
+            }
+            if part.ending_lba() > last_usable_lba {
+                return Err(part.context(
+                    GptError::new(ErrorKind::MbrGptOverlap)
+                        .mismatch(last_usable_lba, part.ending_lba()),
+                ));
+            }
+            let kind = part.partition_type();
+            let mut entry = PartEntry {
+                part_type_guid: kind.guid,
+                part_guid: PartUUID::new_v4_with(rng)?,
+                starting_lba: part.starting_lba,
+                ending_lba: part.ending_lba(),
+                name: PartName::try_from(kind.name).unwrap_or_default(),
+                ..Default::default()
+            };
+            entry
+                .attributes
+                .set_legacy_bios_bootable(part.is_bootable());
+            table.add_partition(entry)?;
+        }
+        Ok(table)
+    }
+
+    /// Follow the chain of EBRs that starts at the beginning of `extended`.
+    ///
+    /// The first record of an EBR describes a logical partition relative to the EBR, the second
+    /// one the next EBR relative to the start of `extended`.
+    fn read_logicals<D: BlockDevice + ?Sized>(
+        dev: &D,
+        extended: &LegacyPart,
+        sector: &mut [u8],
+        parts: &mut Vec<LegacyPart>,
+    ) -> Result<(), GptError> {
+        let mut visited = Vec::new();
+        let mut ebr_lba = extended.starting_lba;
+        loop {
+            let context = |err: GptError| err.inside(Structure::Ebr, 0).read_from(ebr_lba);
+            if visited.contains(&ebr_lba) || visited.len() == MAX_LOGICAL_PARTS {
+                return Err(context(GptError::new(ErrorKind::MbrEbrChain)));
+            }
+            visited.push(ebr_lba);
+            dev.read_blocks(ebr_lba, sector)
+                .and_then(|_| check_sector_signature(sector))
+                .map_err(context)?;
+
+            let [logical, next, ..] = read_records(sector);
+            if Self::is_used(&logical) {
+                let part = LegacyPart {
+                    number: FIRST_LOGICAL_NUMBER
+                        + parts
+                            .iter()
+                            .filter(|part| part.kind == LegacyPartKind::Logical)
+                            .count() as u32,
+                    kind: LegacyPartKind::Logical,
+                    record: logical,
+                    starting_lba: ebr_lba + logical.starting_lba() as u64,
+                    table_lba: ebr_lba,
+                };
+                if part.starting_lba <= ebr_lba || part.ending_lba() > extended.ending_lba() {
+                    return Err(context(
+                        GptError::new(ErrorKind::MbrPROutOfRange).at(record_offset(0)),
+                    ));
+                }
+                parts.push(part);
+            }
+            if !next.is_extended() || next.size_in_lba() == 0 {
+                return Ok(());
+            }
+            let next_lba = extended.starting_lba + next.starting_lba() as u64;
+            if next_lba > extended.ending_lba() {
+                return Err(context(
+                    GptError::new(ErrorKind::MbrPROutOfRange).at(record_offset(1)),
+                ));
+            }
+            ebr_lba = next_lba;
+        }
+    }
+
+    /// A record is used if it has a type and covers at least one block.
+    fn is_used(record: &MbrPartRecord) -> bool {
+        record.os_type() != 0 && record.size_in_lba() != 0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        MemDevice, Serialize,
+        fixture::{LBS, NUM_BLOCKS, mem_device, read_block},
+    };
+    use alloc::string::ToString;
+
+    /// Counts up instead of returning random bytes, so that the GUIDs are known.
+    struct CountingRng(u8);
+
+    impl GuidRng for CountingRng {
+        fn fill_bytes(&mut self, buf: &mut [u8]) -> Result<(), GptError> {
+            self.0 += 1;
+            buf.fill(self.0);
+            Ok(())
+        }
+    }
+
+    /// An MBR or EBR sector with `records`.
+    fn sector(records: &[MbrPartRecord]) -> Vec<u8> {
+        let mut sector = vec![0; LBS as usize];
+        for (index, record) in records.iter().enumerate() {
+            record
+                .serialize_into(&mut sector[record_offset(index)..][..MbrPartRecord::SIZE])
+                .unwrap();
+        }
+        sector[510..512].copy_from_slice(&[0x55, 0xAA]);
+        sector
+    }
+
+    /// A disk with `sectors` written at their LBAs.
+    fn disk(sectors: &[(u64, Vec<u8>)]) -> MemDevice<Vec<u8>> {
+        let mut dev = mem_device(NUM_BLOCKS);
+        for (lba, sector) in sectors {
+            dev.write_blocks(*lba, sector).unwrap();
+        }
+        dev
+    }
+
+    /// A bootable Linux partition at LBA 2048 and an extended partition at LBA 8192 with a Linux
+    /// and a swap logical partition.
+    fn legacy_disk() -> MemDevice<Vec<u8>> {
+        disk(&[
+            (
+                0,
+                sector(&[
+                    MbrPartRecord::new(0x83, 2048, 2048).with_boot_indicator(true),
+                    MbrPartRecord::new(0x0F, 8192, 8000),
+                ]),
+            ),
+            (
+                8192,
+                sector(&[
+                    MbrPartRecord::new(0x83, 2048, 1000),
+                    MbrPartRecord::new(0x05, 4096, 2048),
+                ]),
+            ),
+            (12288, sector(&[MbrPartRecord::new(0x82, 2048, 500)])),
+        ])
+    }
+
+    fn builder() -> GptBuilder {
+        GptBuilder::new(NUM_BLOCKS, LBS, PartUUID::from_u128(1))
+    }
+
+    #[test]
+    fn convert_to_gpt() {
+        let mbr = LegacyMbr::read(&legacy_disk()).unwrap();
+        let table = mbr.to_gpt_with(builder(), &mut CountingRng(0)).unwrap();
+        assert!(table.mbr().part_records[0].is_protective());
+
+        let entries = &table.part_table().entries;
+        let ranges = entries[..4]
+            .iter()
+            .map(|entry| (entry.kind(), entry.starting_lba, entry.ending_lba))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ranges,
+            [
+                (Some(PartitionType::LINUX_FILESYSTEM), 2048, 4095),
+                (Some(PartitionType::LINUX_FILESYSTEM), 10240, 11239),
+                (Some(PartitionType::LINUX_SWAP), 14336, 14835),
+                (None, 0, 0),
+            ]
+        );
+        assert!(entries[0].attributes.is_legacy_bios_bootable());
+        assert!(!entries[1].attributes.is_legacy_bios_bootable());
+        assert_eq!(entries[2].name.to_string(), "Linux swap");
+        assert_eq!(
+            entries[1].part_guid,
+            PartUUID::from_u128(u128::from_le_bytes([2; 16])).with_version(4)
+        );
+    }
+
+    #[test]
+    fn map_mbr_types() {
+        let kind = |os_type| {
+            LegacyPart {
+                number: 1,
+                kind: LegacyPartKind::Primary,
+                record: MbrPartRecord::new(os_type, 2048, 2048),
+                starting_lba: 2048,
+                table_lba: 0,
+            }
+            .partition_type()
+        };
+        assert_eq!(kind(0x07), PartitionType::MICROSOFT_BASIC_DATA);
+        assert_eq!(kind(0x0C), PartitionType::MICROSOFT_BASIC_DATA);
+        assert_eq!(kind(0x82), PartitionType::LINUX_SWAP);
+        assert_eq!(kind(0x83), PartitionType::LINUX_FILESYSTEM);
+        assert_eq!(kind(0x8E), PartitionType::LINUX_LVM);
+        assert_eq!(kind(0xEF), PartitionType::EFI_SYSTEM);
+        assert_eq!(kind(0xFD), PartitionType::LINUX_RAID);
+        assert_eq!(kind(0x9F), PartitionType::LINUX_FILESYSTEM);
+    }
+
+    #[test]
+    fn reject_partition_over_primary_gpt() {
+        let dev = disk(&[(0, sector(&[MbrPartRecord::new(0x83, 1, 4095)]))]);
+        let mbr = LegacyMbr::read(&dev).unwrap();
+        let err = mbr
+            .to_gpt_with(builder(), &mut CountingRng(0))
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::MbrGptOverlap);
+        assert_eq!(err.structure(), Some(Structure::Mbr));
+        assert_eq!(err.offset(), Some(record_offset(0) as _));
+        assert_eq!((err.expected(), err.found()), (Some(34), Some(1)));
+    }
+
+    #[test]
+    fn reject_logical_partition_over_backup_gpt() {
+        let dev = disk(&[
+            (
+                0,
+                sector(&[
+                    MbrPartRecord::new(0x83, 2048, 2048),
+                    MbrPartRecord::new(0x05, 8192, NUM_BLOCKS as u32 - 8192),
+                ]),
+            ),
+            (
+                8192,
+                sector(&[MbrPartRecord::new(0x83, 2048, NUM_BLOCKS as u32 - 10240)]),
+            ),
+        ]);
+        let mbr = LegacyMbr::read(&dev).unwrap();
+        let err = mbr
+            .to_gpt_with(builder(), &mut CountingRng(0))
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::MbrGptOverlap);
+        assert_eq!(err.structure(), Some(Structure::Ebr));
+        assert_eq!(err.lba(), Some(8192));
+        assert_eq!(
+            (err.expected(), err.found()),
+            (Some(NUM_BLOCKS - 34), Some(NUM_BLOCKS - 1))
+        );
+    }
+
+    #[test]
+    fn read_ebr_chain() {
+        let mut dev = legacy_disk();
+        let mut mbr = read_block(&dev, 0);
+        mbr[DISK_SIGNATURE_OFFSET..][..4].copy_from_slice(&0x12345678_u32.to_le_bytes());
+        dev.write_blocks(0, &mbr).unwrap();
+
+        let mbr = LegacyMbr::read(&dev).unwrap();
+        assert_eq!(mbr.disk_signature, 0x12345678);
+        let parts = mbr
+            .parts
+            .iter()
+            .map(|part| (part.number, part.kind, part.starting_lba, part.table_lba))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            parts,
+            [
+                (1, LegacyPartKind::Primary, 2048, 0),
+                (2, LegacyPartKind::Extended, 8192, 0),
+                (5, LegacyPartKind::Logical, 10240, 8192),
+                (6, LegacyPartKind::Logical, 14336, 12288),
+            ]
+        );
+        assert_eq!(mbr.partitions().count(), 3);
+        assert_eq!(mbr.parts[3].ending_lba(), 14835);
+        assert!(mbr.parts[0].is_bootable());
+    }
+
+    /// A disk whose extended partition at LBA 8192 holds `ebr`.
+    fn extended_disk(ebr: &[MbrPartRecord]) -> MemDevice<Vec<u8>> {
+        disk(&[
+            (0, sector(&[MbrPartRecord::new(0x0F, 8192, 8000)])),
+            (8192, sector(ebr)),
+        ])
+    }
+
+    #[test]
+    fn reject_ebr_loop() {
+        let dev = extended_disk(&[
+            MbrPartRecord::new(0x83, 2048, 1000),
+            MbrPartRecord::new(0x05, 0, 4096),
+        ]);
+        let err = LegacyMbr::read(&dev).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::MbrEbrChain);
+        assert_eq!(err.structure(), Some(Structure::Ebr));
+        assert_eq!(err.lba(), Some(8192));
+    }
+
+    #[test]
+    fn reject_next_ebr_outside_extended_partition() {
+        let dev = extended_disk(&[
+            MbrPartRecord::new(0x83, 2048, 1000),
+            MbrPartRecord::new(0x05, 8000, 2048),
+        ]);
+        let err = LegacyMbr::read(&dev).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::MbrPROutOfRange);
+        assert_eq!(err.structure(), Some(Structure::Ebr));
+        assert_eq!(err.offset(), Some(record_offset(1) as _));
+        assert_eq!(err.lba(), Some(8192));
+    }
+
+    #[test]
+    fn reject_logical_partition_outside_extended_partition() {
+        for logical in [
+            MbrPartRecord::new(0x83, 2048, 6000),
+            MbrPartRecord::new(0x83, 0, 1000),
+        ] {
+            let err = LegacyMbr::read(&extended_disk(&[logical])).err().unwrap();
+            assert_eq!(err.kind(), ErrorKind::MbrPROutOfRange);
+            assert_eq!(err.structure(), Some(Structure::Ebr));
+            assert_eq!(err.offset(), Some(record_offset(0) as _));
+        }
+    }
+
+    #[test]
+    fn reject_primary_partition_outside_disk() {
+        let dev = disk(&[(
+            0,
+            sector(&[
+                MbrPartRecord::new(0x83, 2048, 2048),
+                MbrPartRecord::new(0x83, 16000, 1000),
+            ]),
+        )]);
+        let err = LegacyMbr::read(&dev).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::MbrPROutOfRange);
+        assert_eq!(err.structure(), Some(Structure::Mbr));
+        assert_eq!(err.offset(), Some(record_offset(1) as _));
+        assert_eq!(err.lba(), Some(0));
+    }
+
+    #[test]
+    fn reject_missing_signature() {
+        let err = LegacyMbr::read(&disk(&[])).err().unwrap();
+        assert_eq!(err.structure(), Some(Structure::Mbr));
+        assert_eq!(err.lba(), Some(0));
+    }
+}
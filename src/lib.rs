@@ -1,20 +1,48 @@
 //! GUID Partition Table Resolver.
 //!
 //! Little Endian
+//!
+//! The crate is `no_std` and requires `alloc`. The default `std` feature adds [`FileDevice`],
+//! [`OsRng`] for random GUIDs, and the `std::error::Error` implementation of [`GptError`].
+//! The optional `uuid` feature converts [`PartUUID`] to and from `uuid::Uuid`, and the optional
+//! `derive` feature derives reproducible GUIDs from a seed with HMAC-SHA256.
+#![no_std]
 
+mod align;
+mod attr;
+mod builder;
+mod dev;
 mod entry;
 mod err;
+#[cfg(test)]
+mod fixture;
 mod gpt;
 mod hdr;
+mod hybrid;
+mod kind;
+mod legacy;
 mod mbr;
 mod parse;
 mod uuid;
 
-pub use err::GptError;
-pub use gpt::GuidPartTable;
-pub use hdr::Header;
-pub use mbr::{MbrPartRecord, ProtectiveMbr};
+pub use align::Alignment;
+pub use attr::{PartAttributes, TypeAttribute};
+pub use builder::GptBuilder;
+#[cfg(feature = "std")]
+pub use dev::FileDevice;
+pub use dev::{BlockDevice, MemDevice};
+pub use entry::{EntryIter, EntryView, PartEntry, PartName, PartTableEntry};
+pub use err::{ErrorKind, GptError, Structure};
+pub use gpt::{FreeRegion, GptCopy, GuidPartTable, LogicalBlockSize};
+pub use hdr::{Header, HeaderView};
+pub use hybrid::{HybridMapping, HybridPart};
+pub use kind::{PartitionOs, PartitionType};
+pub use legacy::{LegacyMbr, LegacyPart, LegacyPartKind};
+pub use mbr::{MbrKind, MbrPartRecord, MbrReport, ProtectiveMbr};
+pub use parse::Serialize;
 use parse::*;
 pub use uuid::*;
 
 extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
@@ -1,5 +1,7 @@
-use crate::{Deserialize, GptError, LittleEndianBytes, Serialize};
-use core::mem;
+use crate::{
+    Deserialize, ErrorKind, GptError, LittleEndianBytes, Serialize, Structure, check_buf, read_u32,
+};
+use alloc::{vec, vec::Vec};
 // pub const PROTECTIVE_MBR_LBA: usize = 0;
 
 const BOOT_CODE_OFFSET: usize = 0;
@@ -39,11 +41,42 @@ pub struct ProtectiveMbr {
 }
 
 impl ProtectiveMbr {
+    /// Create a protective MBR for a disk of `num_blocks` logical blocks.
+    ///
+    /// The single 0xEE record covers LBA 1 to the end of the disk, with `size_in_lba` clamped to
+    /// 0xFFFFFFFF and the ending CHS set to 0xFFFFFF when the disk is too large for CHS.
+    pub fn new(num_blocks: u64) -> Self {
+        let mut part_records = [MbrPartRecord::default(); PART_RECORD_NUM];
+        part_records[0] = MbrPartRecord::protective(num_blocks);
+        Self {
+            boot_code: [0; BOOT_CODE_SIZE],
+            disk_signature: DISK_SIGNATURE,
+            unknown: UNKNOWN,
+            part_records,
+            signature: SIGNATURE,
+        }
+    }
+
+    /// Read the MBR in `sector` of at least 512 bytes without checking any field.
+    pub(crate) fn from_sector(sector: &[u8]) -> Self {
+        let mut boot_code = [0; BOOT_CODE_SIZE];
+        boot_code.copy_from_slice(&sector[BOOT_CODE_OFFSET..][..BOOT_CODE_SIZE]);
+        let mut disk_signature = [0; DISK_SIGNATURE_SIZE];
+        disk_signature.copy_from_slice(&sector[DISK_SIGNATURE_OFFSET..][..DISK_SIGNATURE_SIZE]);
+        Self {
+            boot_code,
+            disk_signature,
+            unknown: u16::from_le_bytes([sector[UNKNOWN_OFFSET], sector[UNKNOWN_OFFSET + 1]]),
+            part_records: read_records(sector),
+            signature: u16::from_le_bytes([sector[SIGNATURE_OFFSET], sector[SIGNATURE_OFFSET + 1]]),
+        }
+    }
+
     fn check_disk_signature(disk_signature: &[u8]) -> Result<(), GptError> {
         if disk_signature.eq(&DISK_SIGNATURE) {
             Ok(())
         } else {
-            Err(GptError::MbrDiskSignature)
+            Err(GptError::new(ErrorKind::MbrDiskSignature).at(DISK_SIGNATURE_OFFSET))
         }
     }
 
@@ -51,7 +84,9 @@ impl ProtectiveMbr {
         if unknown == UNKNOWN {
             Ok(())
         } else {
-            Err(GptError::MbrUnknownNonZero)
+            Err(GptError::new(ErrorKind::MbrUnknownNonZero)
+                .at(UNKNOWN_OFFSET)
+                .mismatch(UNKNOWN as _, unknown as _))
         }
     }
 
@@ -59,7 +94,37 @@ impl ProtectiveMbr {
         if signature == SIGNATURE {
             Ok(())
         } else {
-            Err(GptError::MbrSignature)
+            Err(GptError::new(ErrorKind::MbrSignature)
+                .at(SIGNATURE_OFFSET)
+                .mismatch(SIGNATURE as _, signature as _))
+        }
+    }
+
+    /// Replace the boot code, e.g. to keep the BIOS boot loader of the MBR being replaced.
+    pub fn with_boot_code(mut self, boot_code: [u8; BOOT_CODE_SIZE]) -> Self {
+        self.boot_code = boot_code;
+        self
+    }
+
+    /// Mark the protective partition record active with boot indicator 0x80.
+    ///
+    /// UEFI requires 0x00, but some legacy BIOS firmware refuses to boot a disk without an
+    /// active partition.
+    pub fn with_boot_indicator(mut self, active: bool) -> Self {
+        self.part_records[0].boot_indicator = if active { BOOTABLE } else { 0 };
+        self
+    }
+
+    /// Make the 0xEE record cover a disk that now has `num_blocks` logical blocks, keeping its
+    /// boot indicator and the rest of the sector.
+    pub(crate) fn resize(&mut self, num_blocks: u64) {
+        if let Some(record) = self
+            .part_records
+            .iter_mut()
+            .find(|record| record.is_protective())
+        {
+            let active = record.boot_indicator == BOOTABLE;
+            *record = MbrPartRecord::protective(num_blocks).with_boot_indicator(active);
         }
     }
 
@@ -70,63 +135,245 @@ impl ProtectiveMbr {
 }
 
 impl Serialize for ProtectiveMbr {
-    fn serialize(&self, size: usize) -> Vec<u8> {
-        let mut bytes = vec![0; size];
+    const SIZE: usize = SIGNATURE_OFFSET + SIGNATURE_SIZE;
+
+    fn serialize_into(&self, bytes: &mut [u8]) -> Result<(), GptError> {
+        check_buf(bytes, Self::SIZE)?;
         bytes[..BOOT_CODE_SIZE].copy_from_slice(&self.boot_code);
         bytes[DISK_SIGNATURE_OFFSET..DISK_SIGNATURE_OFFSET + DISK_SIGNATURE_SIZE]
             .copy_from_slice(&self.disk_signature);
         bytes[UNKNOWN_OFFSET..UNKNOWN_OFFSET + UNKNOWN_SIZE]
             .copy_from_slice(&self.unknown.to_le_bytes());
 
-        self.part_records
-            .iter()
-            .enumerate()
-            .for_each(|(index, record)| {
-                let start = PART_RECORD_OFFSET + index * PART_RECORD_SIZE;
-                bytes[start..start + PART_RECORD_SIZE]
-                    .copy_from_slice(&record.serialize(PART_RECORD_SIZE));
-            });
+        for (index, record) in self.part_records.iter().enumerate() {
+            let start = PART_RECORD_OFFSET + index * PART_RECORD_SIZE;
+            record.serialize_into(&mut bytes[start..start + PART_RECORD_SIZE])?;
+        }
         bytes[SIGNATURE_OFFSET..SIGNATURE_OFFSET + SIGNATURE_SIZE]
             .copy_from_slice(&self.signature.to_le_bytes());
-
-        bytes
+        Ok(())
     }
 }
 
 impl Deserialize for ProtectiveMbr {
+    /// Read a protective or hybrid MBR, failing on the first rule of
+    /// [`MbrReport::classify`] that it breaks. The sizes of the records are not checked because
+    /// the size of the disk is unknown.
     fn deserialize(data: &[u8]) -> Result<Self, crate::GptError> {
-        let mut ltbs = LittleEndianBytes::from(data);
+        let report = MbrReport::check(data, None)?;
+        if let Some(err) = report.violations.first() {
+            return Err(*err);
+        }
+        if report.kind == MbrKind::Legacy {
+            return Err(GptError::new(ErrorKind::MbrPROsType)
+                .inside(Structure::Mbr, 0)
+                .at(PART_RECORD_OFFSET + OSTYPE_OFFSET)
+                .mismatch(OSTYPE as _, report.records[0].ostype as _));
+        }
+        Ok(Self::from_sector(data))
+    }
+}
 
-        let boot_code = ltbs.copy_from::<BOOT_CODE_SIZE>(BOOT_CODE_OFFSET);
+/// Byte offset of the partition record at `index` in the MBR.
+pub(crate) fn record_offset(index: usize) -> usize {
+    PART_RECORD_OFFSET + index * PART_RECORD_SIZE
+}
 
-        let disk_signature = ltbs.copy_from::<DISK_SIGNATURE_SIZE>(DISK_SIGNATURE_OFFSET);
-        Self::check_disk_signature(&disk_signature)?;
+/// Read the four partition records of an MBR or EBR `sector` without checking any field.
+///
+/// `sector` must hold at least 512 bytes.
+pub(crate) fn read_records(sector: &[u8]) -> [MbrPartRecord; PART_RECORD_NUM] {
+    let mut records = [MbrPartRecord::default(); PART_RECORD_NUM];
+    for (index, record) in records.iter_mut().enumerate() {
+        let mut bytes = [0; PART_RECORD_SIZE];
+        bytes.copy_from_slice(&sector[record_offset(index)..][..PART_RECORD_SIZE]);
+        *record = MbrPartRecord::from_bytes(&bytes);
+    }
+    records
+}
 
-        let unknown = ltbs.parse_u16().unwrap();
-        Self::check_unknown(unknown)?;
+/// Check the 0xAA55 signature at the end of an MBR or EBR `sector` of at least 512 bytes.
+pub(crate) fn check_sector_signature(sector: &[u8]) -> Result<(), GptError> {
+    ProtectiveMbr::check_signature(u16::from_le_bytes([
+        sector[SIGNATURE_OFFSET],
+        sector[SIGNATURE_OFFSET + 1],
+    ]))
+}
 
-        let mut part_records = [MbrPartRecord::default(); PART_RECORD_NUM];
-        for (index, record) in part_records.iter_mut().enumerate() {
-            let rd = MbrPartRecord::deserialize(
-                &ltbs.copy_from::<PART_RECORD_SIZE>(PART_RECORD_OFFSET + PART_RECORD_SIZE * index),
-            )?;
-            let _ = mem::replace(record, rd);
-        }
+/// Heads per cylinder and sectors per track of the geometry used to encode CHS addresses.
+const CHS_HEADS: u64 = 255;
+const CHS_SECTORS: u64 = 63;
+const CHS_MAX_CYLINDER: u64 = 1023;
 
-        let signature = ltbs.parse_u16().unwrap();
-        Self::check_signature(signature)?;
+/// Encode `lba` as a CHS address, or 0xFFFFFF if it is beyond the last cylinder.
+pub(crate) fn lba_to_chs(lba: u64) -> [u8; 3] {
+    let cylinder = lba / (CHS_HEADS * CHS_SECTORS);
+    if cylinder > CHS_MAX_CYLINDER {
+        return MAX_ENDING_CHD;
+    }
+    let head = lba / CHS_SECTORS % CHS_HEADS;
+    let sector = lba % CHS_SECTORS + 1;
+    [
+        head as u8,
+        ((cylinder >> 8) << 6 | sector) as u8,
+        cylinder as u8,
+    ]
+}
+
+/// The kind of MBR found in LBA 0 of a disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MbrKind {
+    /// A single 0xEE record that protects the GPT.
+    Protective,
+    /// A 0xEE record next to legacy records that mirror GPT partitions.
+    Hybrid,
+    /// A DOS partition table without a 0xEE record.
+    Legacy,
+    /// The sector does not end with the 0xAA55 signature, or could not be read.
+    NotMbr,
+}
+
+/// The classification of an MBR sector and the rules of its kind that it breaks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MbrReport {
+    kind: MbrKind,
+    records: [MbrPartRecord; PART_RECORD_NUM],
+    violations: Vec<GptError>,
+}
+
+impl MbrReport {
+    /// Classify the MBR in `sector`, read from LBA 0 of a disk of `num_blocks` logical blocks.
+    ///
+    /// Only a sector shorter than 512 bytes is an error, broken rules are recorded as
+    /// [`violations`](Self::violations) instead.
+    pub fn classify(sector: &[u8], num_blocks: u64) -> Result<Self, GptError> {
+        Self::check(sector, Some(num_blocks))
+    }
 
+    /// Classify the MBR in `sector`, checking the sizes of the records only if the number of
+    /// logical blocks of the disk is known.
+    fn check(sector: &[u8], num_blocks: Option<u64>) -> Result<Self, GptError> {
+        if sector.len() < ProtectiveMbr::SIZE {
+            return Err(GptError::new(ErrorKind::Truncated)
+                .inside(Structure::Mbr, 0)
+                .mismatch(ProtectiveMbr::SIZE as _, sector.len() as _));
+        }
+        let mut violations = Vec::new();
+        if let Err(err) = check_sector_signature(sector) {
+            violations.push(err.inside(Structure::Mbr, 0));
+            return Ok(Self {
+                kind: MbrKind::NotMbr,
+                records: [MbrPartRecord::default(); PART_RECORD_NUM],
+                violations,
+            });
+        }
+
+        let records = read_records(sector);
+        let protective = records.iter().any(|record| record.ostype == OSTYPE);
+        let legacy = records
+            .iter()
+            .any(|record| record.ostype != UNUSED_OSTYPE && record.ostype != OSTYPE);
+        let kind = match (protective, legacy) {
+            (false, _) => MbrKind::Legacy,
+            (true, false) => MbrKind::Protective,
+            (true, true) => MbrKind::Hybrid,
+        };
+
+        for (index, record) in records.iter().enumerate() {
+            let mut check = |result: Result<(), GptError>| {
+                if let Err(err) = result {
+                    violations.push(err.inside(Structure::Mbr, record_offset(index)));
+                }
+            };
+            // A record of type zero is unused and must be all zero.
+            if record.ostype == UNUSED_OSTYPE {
+                if !record.is_empty() {
+                    check(Err(GptError::new(ErrorKind::MbrNonZeroRecord).at(0)));
+                }
+                continue;
+            }
+            if record.ostype == OSTYPE {
+                // Only one record may protect the GPT.
+                if records[..index].iter().any(|other| other.ostype == OSTYPE) {
+                    check(Err(GptError::new(ErrorKind::MbrPROsType).at(OSTYPE_OFFSET)));
+                }
+                check(MbrPartRecord::check_starting_chs(&record.starting_chs));
+                check(MbrPartRecord::check_starting_lba(record.starting_lba));
+                check(MbrPartRecord::check_boot_indicator(
+                    record.boot_indicator,
+                    false,
+                ));
+            } else {
+                check(MbrPartRecord::check_boot_indicator(
+                    record.boot_indicator,
+                    true,
+                ));
+            }
+            if let Some(num_blocks) = num_blocks {
+                if kind == MbrKind::Protective {
+                    check(record.check_size_in_lba(num_blocks));
+                } else if record.starting_lba as u64 + record.size_in_lba as u64 > num_blocks {
+                    check(Err(
+                        GptError::new(ErrorKind::MbrPROutOfRange).at(SIZE_IN_LBA_OFFSET)
+                    ));
+                }
+            }
+            if records[..index].iter().any(|other| other.overlaps(record)) {
+                check(Err(
+                    GptError::new(ErrorKind::MbrPROverlap).at(STARTING_LBA_OFFSET)
+                ));
+            }
+        }
+        if kind != MbrKind::Legacy {
+            let mbr = ProtectiveMbr::from_sector(sector);
+            // Hybrid MBRs usually keep the disk signature of the legacy partition table.
+            if kind == MbrKind::Protective
+                && let Err(err) = ProtectiveMbr::check_disk_signature(&mbr.disk_signature)
+            {
+                violations.push(err.inside(Structure::Mbr, 0));
+            }
+            if let Err(err) = ProtectiveMbr::check_unknown(mbr.unknown) {
+                violations.push(err.inside(Structure::Mbr, 0));
+            }
+        }
         Ok(Self {
-            boot_code,
-            disk_signature,
-            unknown,
-            part_records,
-            signature,
+            kind,
+            records,
+            violations,
         })
     }
+
+    /// The report of a sector that could not be read because of `err`.
+    pub(crate) fn unreadable(err: GptError) -> Self {
+        Self {
+            kind: MbrKind::NotMbr,
+            records: [MbrPartRecord::default(); PART_RECORD_NUM],
+            violations: vec![err],
+        }
+    }
+
+    pub fn kind(&self) -> MbrKind {
+        self.kind
+    }
+
+    /// The four partition records, all zero if the sector is not an MBR.
+    pub fn records(&self) -> &[MbrPartRecord; PART_RECORD_NUM] {
+        &self.records
+    }
+
+    /// The rules of the MBR kind that the sector breaks, each with its byte offset.
+    pub fn violations(&self) -> &[GptError] {
+        &self.violations
+    }
+
+    /// Whether the sector follows all rules of its kind.
+    pub fn is_valid(&self) -> bool {
+        self.kind != MbrKind::NotMbr && self.violations.is_empty()
+    }
 }
 
 const BOOT_INDICATOR_OFFSET: usize = 0;
+const BOOTABLE: u8 = 0x80;
 
 const STARTING_CHS_OFFSET: usize = 1;
 const STARTING_CHS_SIZE: usize = 3;
@@ -134,10 +381,12 @@ const STARTING_CHS: [u8; STARTING_CHS_SIZE] = [00, 0x02, 00];
 
 const OSTYPE_OFFSET: usize = 4;
 const OSTYPE: u8 = 0xEE;
+const UNUSED_OSTYPE: u8 = 0x00;
+/// CHS, LBA and Linux extended partitions.
+const EXTENDED_OSTYPES: [u8; 3] = [0x05, 0x0F, 0x85];
 
 const ENDING_CHS_OFFSET: usize = 5;
 const ENDING_CHS_SIZE: usize = 3;
-const ENDING_CHS: [u8; ENDING_CHS_SIZE] = STARTING_CHS;
 const MAX_ENDING_CHD: [u8; ENDING_CHS_SIZE] = [0xFF, 0xFF, 0xFF];
 
 const STARTING_LBA_OFFSET: usize = 8;
@@ -169,11 +418,115 @@ pub struct MbrPartRecord {
 }
 
 impl MbrPartRecord {
+    /// OS type of the record that protects the GPT.
+    pub const PROTECTIVE_OS_TYPE: u8 = OSTYPE;
+
+    /// Create a record of `os_type` for `size_in_lba` blocks at `starting_lba`, with CHS
+    /// addresses in the usual geometry of 255 heads and 63 sectors per track.
+    pub fn new(os_type: u8, starting_lba: u32, size_in_lba: u32) -> Self {
+        let last_lba = (starting_lba as u64 + size_in_lba as u64).saturating_sub(1);
+        Self {
+            boot_indicator: 0,
+            starting_chs: lba_to_chs(starting_lba as _),
+            ostype: os_type,
+            ending_chs: lba_to_chs(last_lba.max(starting_lba as _)),
+            starting_lba,
+            size_in_lba,
+        }
+    }
+
+    /// Mark the record active with boot indicator 0x80.
+    pub fn with_boot_indicator(mut self, active: bool) -> Self {
+        self.boot_indicator = if active { BOOTABLE } else { 0 };
+        self
+    }
+
+    /// Whether this record is an extended partition that holds a chain of EBRs.
+    pub fn is_extended(&self) -> bool {
+        EXTENDED_OSTYPES.contains(&self.ostype)
+    }
+
+    /// Whether this is a 0xEE record that protects the GPT.
+    pub fn is_protective(&self) -> bool {
+        self.ostype == OSTYPE
+    }
+
+    /// Set to 0x80 for the active partition of a legacy MBR.
+    pub fn boot_indicator(&self) -> u8 {
+        self.boot_indicator
+    }
+
+    /// CHS address of the first block, as stored on disk.
+    pub fn starting_chs(&self) -> [u8; STARTING_CHS_SIZE] {
+        self.starting_chs
+    }
+
+    /// Partition type code, e.g. 0xEE for a GPT protective partition.
+    pub fn os_type(&self) -> u8 {
+        self.ostype
+    }
+
+    /// CHS address of the last block, as stored on disk.
+    pub fn ending_chs(&self) -> [u8; ENDING_CHS_SIZE] {
+        self.ending_chs
+    }
+
+    pub fn starting_lba(&self) -> u32 {
+        self.starting_lba
+    }
+
+    pub fn size_in_lba(&self) -> u32 {
+        self.size_in_lba
+    }
+
+    /// Whether the record is all zero, i.e. unused.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Whether the record shares any logical block with `other`.
+    pub fn overlaps(&self, other: &MbrPartRecord) -> bool {
+        let end = |record: &Self| record.starting_lba as u64 + record.size_in_lba as u64;
+        self.size_in_lba > 0
+            && other.size_in_lba > 0
+            && (self.starting_lba as u64) < end(other)
+            && (other.starting_lba as u64) < end(self)
+    }
+
+    /// Read a record without checking any field.
+    fn from_bytes(bytes: &[u8; PART_RECORD_SIZE]) -> Self {
+        let mut starting_chs = [0; STARTING_CHS_SIZE];
+        starting_chs.copy_from_slice(&bytes[STARTING_CHS_OFFSET..][..STARTING_CHS_SIZE]);
+        let mut ending_chs = [0; ENDING_CHS_SIZE];
+        ending_chs.copy_from_slice(&bytes[ENDING_CHS_OFFSET..][..ENDING_CHS_SIZE]);
+        Self {
+            boot_indicator: bytes[BOOT_INDICATOR_OFFSET],
+            starting_chs,
+            ostype: bytes[OSTYPE_OFFSET],
+            ending_chs,
+            starting_lba: read_u32(bytes, STARTING_LBA_OFFSET),
+            size_in_lba: read_u32(bytes, SIZE_IN_LBA_OFFSET),
+        }
+    }
+
+    /// Create the partition record covering the whole disk of `num_blocks` logical blocks.
+    fn protective(num_blocks: u64) -> Self {
+        let last_lba = num_blocks.saturating_sub(1).max(STARTING_LBA as u64);
+        Self {
+            boot_indicator: 0,
+            starting_chs: STARTING_CHS,
+            ostype: OSTYPE,
+            ending_chs: lba_to_chs(last_lba),
+            starting_lba: STARTING_LBA,
+            size_in_lba: num_blocks.saturating_sub(1).min(MAX_SIZE_IN_LBA as u64) as u32,
+        }
+    }
+
     fn check_starting_chs(starting_chs: &[u8]) -> Result<(), GptError> {
         if starting_chs.eq(&STARTING_CHS) {
             Ok(())
         } else {
-            Err(GptError::MbrPRStartingChs)
+            Err(GptError::new(ErrorKind::MbrPRStartingChs).at(STARTING_CHS_OFFSET))
         }
     }
 
@@ -181,15 +534,32 @@ impl MbrPartRecord {
         if ostype == OSTYPE {
             Ok(())
         } else {
-            Err(GptError::MbrPROsType)
+            Err(GptError::new(ErrorKind::MbrPROsType)
+                .at(OSTYPE_OFFSET)
+                .mismatch(OSTYPE as _, ostype as _))
+        }
+    }
+
+    /// Legacy records may be marked active with 0x80, the GPT protective record may not.
+    fn check_boot_indicator(boot_indicator: u8, bootable: bool) -> Result<(), GptError> {
+        if boot_indicator == 0 || (bootable && boot_indicator == BOOTABLE) {
+            Ok(())
+        } else {
+            Err(GptError::new(ErrorKind::MbrPRBootIndicator)
+                .at(BOOT_INDICATOR_OFFSET)
+                .mismatch(0, boot_indicator as _))
         }
     }
 
-    fn check_ending_chs(ending_chs: &[u8]) -> Result<(), GptError> {
-        if ending_chs.eq(&ENDING_CHS) {
+    /// The protective record covers the disk of `num_blocks` logical blocks after LBA 0.
+    fn check_size_in_lba(&self, num_blocks: u64) -> Result<(), GptError> {
+        let size_in_lba = num_blocks.saturating_sub(1).min(MAX_SIZE_IN_LBA as u64);
+        if self.size_in_lba as u64 == size_in_lba {
             Ok(())
         } else {
-            Err(GptError::MbrPREndingChs)
+            Err(GptError::new(ErrorKind::MbrPRSizeInLba)
+                .at(SIZE_IN_LBA_OFFSET)
+                .mismatch(size_in_lba, self.size_in_lba as _))
         }
     }
 
@@ -197,14 +567,18 @@ impl MbrPartRecord {
         if starting_lba == STARTING_LBA {
             Ok(())
         } else {
-            Err(GptError::MbrPRStartingLba)
+            Err(GptError::new(ErrorKind::MbrPRStartingLba)
+                .at(STARTING_LBA_OFFSET)
+                .mismatch(STARTING_LBA as _, starting_lba as _))
         }
     }
 }
 
 impl Serialize for MbrPartRecord {
-    fn serialize(&self, size: usize) -> Vec<u8> {
-        let mut bytes = vec![0; size];
+    const SIZE: usize = PART_RECORD_SIZE;
+
+    fn serialize_into(&self, bytes: &mut [u8]) -> Result<(), GptError> {
+        check_buf(bytes, Self::SIZE)?;
         bytes[BOOT_INDICATOR_OFFSET] = self.boot_indicator;
         bytes[STARTING_CHS_OFFSET..STARTING_CHS_OFFSET + STARTING_CHS_SIZE]
             .copy_from_slice(&self.starting_chs);
@@ -215,7 +589,7 @@ impl Serialize for MbrPartRecord {
             .copy_from_slice(&self.starting_lba.to_le_bytes());
         bytes[SIZE_IN_LBA_OFFSET..SIZE_IN_LBA_OFFSET + SIZE_IN_LBA_SIZE]
             .copy_from_slice(&self.size_in_lba.to_le_bytes());
-        bytes
+        Ok(())
     }
 }
 
@@ -223,20 +597,20 @@ impl Deserialize for MbrPartRecord {
     fn deserialize(data: &[u8]) -> Result<Self, crate::GptError> {
         let mut ltbs = LittleEndianBytes::from(data);
 
-        let boot_indicator = ltbs.parse_u8().unwrap();
-        let starting_chs = ltbs.copy_from::<STARTING_CHS_SIZE>(STARTING_CHS_OFFSET);
+        let boot_indicator = ltbs.parse_u8()?;
+        let starting_chs = ltbs.copy_from::<STARTING_CHS_SIZE>(STARTING_CHS_OFFSET)?;
         Self::check_starting_chs(&starting_chs)?;
 
-        let ostype = ltbs.parse_u8().unwrap();
+        let ostype = ltbs.parse_u8()?;
         Self::check_ostype(ostype)?;
 
-        let ending_chs = ltbs.copy_from::<ENDING_CHS_SIZE>(ENDING_CHS_OFFSET);
-        Self::check_ending_chs(&ending_chs)?;
+        // The ending CHS depends on the disk geometry, it is not checked.
+        let ending_chs = ltbs.copy_from::<ENDING_CHS_SIZE>(ENDING_CHS_OFFSET)?;
 
-        let starting_lba = ltbs.parse_u32().unwrap();
+        let starting_lba = ltbs.parse_u32()?;
         Self::check_starting_lba(starting_lba)?;
 
-        let size_in_lba = ltbs.parse_u32().unwrap();
+        let size_in_lba = ltbs.parse_u32()?;
         Ok(Self {
             boot_indicator,
             starting_chs,
@@ -247,3 +621,179 @@ impl Deserialize for MbrPartRecord {
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixture::NUM_BLOCKS;
+
+    fn sector(mbr: &ProtectiveMbr, disk_signature: u32) -> Vec<u8> {
+        let mut sector = mbr.serialize(ProtectiveMbr::SIZE);
+        sector[DISK_SIGNATURE_OFFSET..][..DISK_SIGNATURE_SIZE]
+            .copy_from_slice(&disk_signature.to_le_bytes());
+        sector
+    }
+
+    fn hybrid() -> ProtectiveMbr {
+        let mut mbr = ProtectiveMbr::new(NUM_BLOCKS);
+        mbr.part_records[0] = MbrPartRecord::new(OSTYPE, 1, 2047);
+        mbr.part_records[1] = MbrPartRecord::new(0x0C, 2048, 2048);
+        mbr
+    }
+
+    #[test]
+    fn classify_protective() {
+        let report =
+            MbrReport::classify(&sector(&ProtectiveMbr::new(NUM_BLOCKS), 0), NUM_BLOCKS).unwrap();
+        assert_eq!(report.kind(), MbrKind::Protective);
+        assert!(report.is_valid());
+    }
+
+    #[test]
+    fn classify_protective_with_disk_signature() {
+        let sector = sector(&ProtectiveMbr::new(NUM_BLOCKS), 0x12345678);
+        let report = MbrReport::classify(&sector, NUM_BLOCKS).unwrap();
+        assert_eq!(report.kind(), MbrKind::Protective);
+        assert_eq!(report.violations().len(), 1);
+        assert_eq!(report.violations()[0].kind(), ErrorKind::MbrDiskSignature);
+        assert!(ProtectiveMbr::deserialize(&sector).is_err());
+    }
+
+    #[test]
+    fn classify_hybrid_with_disk_signature() {
+        let sector = sector(&hybrid(), 0x12345678);
+        let report = MbrReport::classify(&sector, NUM_BLOCKS).unwrap();
+        assert_eq!(report.kind(), MbrKind::Hybrid);
+        assert!(report.is_valid(), "{:?}", report.violations());
+        assert_eq!(ProtectiveMbr::deserialize(&sector).unwrap(), {
+            let mut mbr = hybrid();
+            mbr.disk_signature = 0x12345678_u32.to_le_bytes();
+            mbr
+        });
+    }
+
+    #[test]
+    fn deserialize_checks_like_classify() {
+        let mut sectors = vec![
+            sector(&ProtectiveMbr::new(NUM_BLOCKS), 0),
+            sector(&hybrid(), 0x12345678),
+            sector(&ProtectiveMbr::new(NUM_BLOCKS), 0x12345678),
+        ];
+        let mut mbr = hybrid();
+        mbr.part_records[2] = MbrPartRecord::new(OSTYPE, 4096, 2048);
+        sectors.push(sector(&mbr, 0));
+        mbr.part_records[2] = MbrPartRecord::new(0x83, 4096, 2048);
+        mbr.part_records[2].boot_indicator = 0x42;
+        sectors.push(sector(&mbr, 0));
+        mbr.part_records[2] = MbrPartRecord::new(0, 4096, 2048);
+        sectors.push(sector(&mbr, 0));
+        mbr.unknown = 1;
+        sectors.push(sector(&mbr, 0));
+
+        for sector in sectors {
+            let report = MbrReport::classify(&sector, NUM_BLOCKS).unwrap();
+            let mbr = ProtectiveMbr::deserialize(&sector);
+            assert_eq!(mbr.as_ref().err(), report.violations().first());
+            if let Ok(mbr) = mbr {
+                assert_eq!(mbr, ProtectiveMbr::from_sector(&sector));
+            }
+        }
+    }
+
+    #[test]
+    fn deserialize_legacy_mbr() {
+        let mut mbr = hybrid();
+        mbr.part_records[0] = MbrPartRecord::default();
+        let sector = sector(&mbr, 0x12345678);
+        assert_eq!(
+            MbrReport::classify(&sector, NUM_BLOCKS).unwrap().kind(),
+            MbrKind::Legacy
+        );
+        let err = ProtectiveMbr::deserialize(&sector).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::MbrPROsType);
+        assert_eq!(
+            err.offset(),
+            Some((record_offset(0) + OSTYPE_OFFSET) as u64)
+        );
+    }
+
+    #[test]
+    fn chs_encoding() {
+        assert_eq!(lba_to_chs(0), [0x00, 0x01, 0x00]);
+        assert_eq!(lba_to_chs(2047), [0x20, 0x20, 0x00]);
+        assert_eq!(lba_to_chs(256 * 16065), [0x00, 0x41, 0x00]);
+        assert_eq!(lba_to_chs(1024 * 16065 - 1), [0xFE, 0xFF, 0xFF]);
+        assert_eq!(lba_to_chs(1024 * 16065), MAX_ENDING_CHD);
+        assert_eq!(lba_to_chs(u64::MAX), MAX_ENDING_CHD);
+    }
+
+    #[test]
+    fn record_chs() {
+        let record = MbrPartRecord::new(0x0C, 2048, 2048);
+        assert_eq!(record.starting_chs(), lba_to_chs(2048));
+        assert_eq!(record.ending_chs(), lba_to_chs(4095));
+        let empty = MbrPartRecord::new(0x0C, 2048, 0);
+        assert_eq!(empty.ending_chs(), empty.starting_chs());
+        let last = MbrPartRecord::new(0x0C, u32::MAX, u32::MAX);
+        assert_eq!(last.ending_chs(), MAX_ENDING_CHD);
+    }
+
+    #[test]
+    fn protective_record() {
+        let mbr = ProtectiveMbr::new(NUM_BLOCKS);
+        let record = &mbr.part_records[0];
+        assert_eq!(record.starting_chs(), STARTING_CHS);
+        assert_eq!(record.ending_chs(), [0x05, 0x04, 0x01]);
+        assert_eq!((record.starting_lba(), record.size_in_lba()), (1, 16383));
+        assert_eq!(record.boot_indicator(), 0);
+        assert!(!mbr.is_large_disk());
+        assert!(mbr.part_records[1..].iter().all(MbrPartRecord::is_empty));
+
+        // Too large for CHS, but not for the 32-bit size.
+        let mbr = ProtectiveMbr::new(1024 * 16065 + 1);
+        assert_eq!(mbr.part_records[0].ending_chs(), MAX_ENDING_CHD);
+        assert_eq!(mbr.part_records[0].size_in_lba(), 1024 * 16065);
+        assert!(!mbr.is_large_disk());
+
+        let mbr = ProtectiveMbr::new(1 << 33);
+        assert_eq!(mbr.part_records[0].ending_chs(), MAX_ENDING_CHD);
+        assert_eq!(mbr.part_records[0].size_in_lba(), MAX_SIZE_IN_LBA);
+        assert!(mbr.is_large_disk());
+    }
+
+    #[test]
+    fn boot_code_and_indicator() {
+        let mbr = ProtectiveMbr::new(NUM_BLOCKS)
+            .with_boot_code([0x90; BOOT_CODE_SIZE])
+            .with_boot_indicator(true);
+        let sector = mbr.serialize(ProtectiveMbr::SIZE);
+        assert!(sector[..BOOT_CODE_SIZE].iter().all(|&b| b == 0x90));
+        assert_eq!(mbr.part_records[0].boot_indicator(), BOOTABLE);
+        assert_eq!(ProtectiveMbr::from_sector(&sector), mbr);
+        assert_eq!(
+            ProtectiveMbr::new(NUM_BLOCKS)
+                .with_boot_indicator(true)
+                .with_boot_indicator(false),
+            ProtectiveMbr::new(NUM_BLOCKS)
+        );
+    }
+
+    #[test]
+    fn truncated_mbr() {
+        let sector = ProtectiveMbr::new(NUM_BLOCKS).serialize(ProtectiveMbr::SIZE);
+        for len in 0..ProtectiveMbr::SIZE {
+            assert!(
+                ProtectiveMbr::deserialize(&sector[..len]).is_err(),
+                "{}",
+                len
+            );
+            assert_eq!(
+                MbrReport::classify(&sector[..len], NUM_BLOCKS)
+                    .err()
+                    .unwrap()
+                    .kind(),
+                ErrorKind::Truncated
+            );
+        }
+    }
+}
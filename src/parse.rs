@@ -1,9 +1,71 @@
-use crate::GptError;
-use alloc::vec::Vec;
-use core::ops::Deref;
+use crate::{ErrorKind, GptError};
+use alloc::{vec, vec::Vec};
+use core::ops::{Deref, Range};
 
-pub(crate) trait Serialize {
-    fn serialize(&self, size: usize) -> Vec<u8>;
+const CRC32: crc::Crc<u32> = crc::Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
+
+/// CRC32 as used by the GPT header and the partition entry array.
+pub(crate) fn crc32(bytes: &[u8]) -> u32 {
+    CRC32.checksum(bytes)
+}
+
+/// CRC32 of `bytes` computed as if the bytes in `zeroed` were set to zero.
+pub(crate) fn crc32_zeroed(bytes: &[u8], zeroed: Range<usize>) -> u32 {
+    let mut digest = CRC32.digest();
+    digest.update(&bytes[..zeroed.start]);
+    zeroed.clone().for_each(|_| digest.update(&[0]));
+    digest.update(&bytes[zeroed.end..]);
+    digest.finalize()
+}
+
+/// CRC32 of `bytes` followed by zeros up to a total of `len` bytes.
+pub(crate) fn crc32_padded(bytes: &[u8], len: usize) -> u32 {
+    let mut digest = CRC32.digest();
+    digest.update(bytes);
+    (bytes.len()..len).for_each(|_| digest.update(&[0]));
+    digest.finalize()
+}
+
+/// Read a little endian `u32` at `offset` of `data`.
+pub(crate) fn read_u32(data: &[u8], offset: usize) -> u32 {
+    let mut bytes = [0; 4];
+    bytes.copy_from_slice(&data[offset..offset + 4]);
+    u32::from_le_bytes(bytes)
+}
+
+/// Read a little endian `u64` at `offset` of `data`.
+pub(crate) fn read_u64(data: &[u8], offset: usize) -> u64 {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&data[offset..offset + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+/// On-disk encoding of a GPT or MBR structure.
+pub trait Serialize {
+    /// Number of bytes the structure occupies.
+    const SIZE: usize;
+
+    /// Write the structure at the start of `buf` and zero the rest of `buf`.
+    ///
+    /// Fails if `buf` is shorter than [`Self::SIZE`].
+    fn serialize_into(&self, buf: &mut [u8]) -> Result<(), GptError>;
+
+    /// Allocate `size` bytes, but at least [`Self::SIZE`], and serialize the structure into them.
+    fn serialize(&self, size: usize) -> Vec<u8> {
+        let mut bytes = vec![0; size.max(Self::SIZE)];
+        self.serialize_into(&mut bytes)
+            .expect("buffer holds the whole structure");
+        bytes
+    }
+}
+
+/// Check that `buf` can hold `size` bytes and zero it.
+pub(crate) fn check_buf(buf: &mut [u8], size: usize) -> Result<(), GptError> {
+    if buf.len() < size {
+        return Err(GptError::new(ErrorKind::Truncated).mismatch(size as _, buf.len() as _));
+    }
+    buf.fill(0);
+    Ok(())
 }
 
 pub(crate) trait Deserialize: Sized {
@@ -25,76 +87,44 @@ impl<'a> From<&'a [u8]> for LittleEndianBytes<'a> {
 }
 
 impl LittleEndianBytes<'_> {
-    pub fn parse_u8(&mut self) -> Option<u8> {
-        if self.cursor < self.data.len() {
-            let res = self.data[self.cursor];
-            self.skip(1);
-            return Some(res);
-        }
-        None
-    }
-
-    pub fn parse_u16(&mut self) -> Option<u16> {
-        if self.cursor + 1 < self.data.len() {
-            let (a0, a1) = (self.data[self.cursor], self.data[self.cursor + 1]);
-            self.skip(2);
-            let res = (a1 as u16) << 8 | a0 as u16;
-            return Some(res);
-        }
-        None
+    pub fn parse_u8(&mut self) -> Result<u8, GptError> {
+        Ok(u8::from_le_bytes(self.take()?))
     }
 
-    pub fn parse_u32(&mut self) -> Option<u32> {
-        if self.cursor + 3 < self.data.len() {
-            let (a0, a1, a2, a3) = (
-                self.data[self.cursor],
-                self.data[self.cursor + 1],
-                self.data[self.cursor + 2],
-                self.data[self.cursor + 3],
-            );
-            self.skip(4);
-            let res = (a3 as u32) << 24 | (a2 as u32) << 16 | (a1 as u32) << 8 | a0 as u32;
-            return Some(res);
-        }
-        None
+    pub fn parse_u32(&mut self) -> Result<u32, GptError> {
+        Ok(u32::from_le_bytes(self.take()?))
     }
 
-    pub fn parse_u64(&mut self) -> Option<u64> {
-        if self.cursor + 7 < self.data.len() {
-            let (a0, a1, a2, a3, a4, a5, a6, a7) = (
-                self.data[self.cursor],
-                self.data[self.cursor + 1],
-                self.data[self.cursor + 2],
-                self.data[self.cursor + 3],
-                self.data[self.cursor + 4],
-                self.data[self.cursor + 5],
-                self.data[self.cursor + 6],
-                self.data[self.cursor + 7],
-            );
-            self.skip(8);
-            let res = (a7 as u64) << 56
-                | (a6 as u64) << 48
-                | (a5 as u64) << 40
-                | (a4 as u64) << 32
-                | (a3 as u64) << 24
-                | (a2 as u64) << 16
-                | (a1 as u64) << 8
-                | a0 as u64;
-            return Some(res);
-        }
-        None
+    pub fn parse_u64(&mut self) -> Result<u64, GptError> {
+        Ok(u64::from_le_bytes(self.take()?))
     }
 
     #[inline]
     pub fn skip(&mut self, size: usize) {
-        self.cursor += size;
+        self.cursor = self.cursor.saturating_add(size);
     }
 
-    pub fn copy_from<const N: usize>(&mut self, offset: usize) -> [u8; N] {
+    /// Copy `N` bytes at `offset` and move the cursor past them.
+    pub fn copy_from<const N: usize>(&mut self, offset: usize) -> Result<[u8; N], GptError> {
+        self.cursor = offset;
+        self.take()
+    }
+
+    /// Copy `N` bytes at the cursor and move the cursor past them.
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], GptError> {
+        let bytes = self
+            .data
+            .get(self.cursor..)
+            .and_then(|rest| rest.get(..N))
+            .ok_or_else(|| {
+                GptError::new(ErrorKind::Truncated)
+                    .at(self.cursor)
+                    .mismatch(self.cursor.saturating_add(N) as _, self.data.len() as _)
+            })?;
         let mut res = [0; N];
-        res.copy_from_slice(&self.data[offset..offset + N]);
+        res.copy_from_slice(bytes);
         self.skip(N);
-        res
+        Ok(res)
     }
 }
 
@@ -107,4 +137,31 @@ impl<'a> Deref for LittleEndianBytes<'a> {
 }
 
 #[cfg(test)]
-mod test {}
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_past_the_end() {
+        let mut ltbs = LittleEndianBytes::from(&[1, 2, 3, 4, 5][..]);
+        assert_eq!(ltbs.parse_u32().unwrap(), 0x04030201);
+        let err = ltbs.parse_u32().err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::Truncated);
+        assert_eq!(err.offset(), Some(4));
+        assert_eq!((err.expected(), err.found()), (Some(8), Some(5)));
+        assert!(ltbs.copy_from::<2>(usize::MAX).is_err());
+    }
+
+    #[test]
+    fn crc32_variants() {
+        let bytes = [0xA5; 100];
+        let mut zeroed = bytes;
+        zeroed[16..20].fill(0);
+        assert_eq!(crc32_zeroed(&bytes, 16..20), crc32(&zeroed));
+        assert_eq!(
+            crc32_padded(&bytes, 128),
+            crc32(&[&bytes[..], &[0; 28]].concat())
+        );
+        assert_eq!(crc32_padded(&bytes, 50), crc32(&bytes));
+        assert_eq!(crc32(b"123456789"), 0xCBF43926);
+    }
+}
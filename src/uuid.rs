@@ -1,4 +1,4 @@
-use crate::GptError;
+use crate::{ErrorKind, GptError};
 use core::{
     fmt::Display,
     ops::{Deref, DerefMut},
@@ -7,21 +7,80 @@ use core::{
 
 pub const UUID_SIZE: usize = 16;
 
+const URN_PREFIX: &[u8] = b"urn:uuid:";
+/// Length of the 8-4-4-4-12 text form.
+const GUID_TEXT_LEN: usize = 36;
+const GUID_TEXT_HYPHENS: [usize; 4] = [8, 13, 18, 23];
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub struct PartUUID([u8; UUID_SIZE]);
 
+impl PartUUID {
+    /// Create a GUID from its 128-bit value as written in the registry format,
+    /// e.g. `0xC12A7328_F81F_11D2_BA4B_00A0C93EC93B`.
+    pub const fn from_u128(value: u128) -> Self {
+        let be = value.to_be_bytes();
+        Self([
+            be[3], be[2], be[1], be[0], be[5], be[4], be[7], be[6], be[8], be[9], be[10], be[11],
+            be[12], be[13], be[14], be[15],
+        ])
+    }
+
+    /// Generate a random RFC 4122 version 4 GUID from the OS random number generator.
+    #[cfg(feature = "std")]
+    pub fn new_v4() -> Result<Self, GptError> {
+        Self::new_v4_with(&mut OsRng)
+    }
+
+    /// Generate a random RFC 4122 version 4 GUID from `rng`.
+    pub fn new_v4_with<R: GuidRng + ?Sized>(rng: &mut R) -> Result<Self, GptError> {
+        let mut bytes = [0; UUID_SIZE];
+        rng.fill_bytes(&mut bytes)?;
+        Ok(Self::from_u128(u128::from_le_bytes(bytes)).with_version(4))
+    }
+
+    /// Derive a version 4 GUID from the HMAC-SHA256 of `label` keyed with `seed`, as
+    /// systemd-repart does with the machine ID, so that the same seed and label always give the
+    /// same GUID.
+    #[cfg(feature = "derive")]
+    pub fn derive(seed: &[u8], label: &[u8]) -> Self {
+        use hmac::Mac;
+        let mut mac = hmac::Hmac::<sha2::Sha256>::new_from_slice(seed)
+            .expect("HMAC accepts keys of any size");
+        mac.update(label);
+        let digest = mac.finalize().into_bytes();
+        let mut bytes = [0; UUID_SIZE];
+        bytes.copy_from_slice(&digest[..UUID_SIZE]);
+        Self::from_u128(u128::from_be_bytes(bytes)).with_version(4)
+    }
+
+    /// Set the version number and the RFC 4122 variant bits.
+    pub(crate) const fn with_version(self, version: u8) -> Self {
+        let value = self.to_u128() & !(0xF << 76) & !(0x3 << 62);
+        Self::from_u128(value | (version as u128 & 0xF) << 76 | 0x2 << 62)
+    }
+
+    /// The version number of an RFC 4122 GUID, e.g. 4 for a random GUID.
+    pub const fn version(&self) -> u8 {
+        (self.to_u128() >> 76) as u8 & 0xF
+    }
+
+    /// The 128-bit value of the GUID as written in the registry format, see [`Self::from_u128`].
+    pub const fn to_u128(&self) -> u128 {
+        let b = self.0;
+        u128::from_be_bytes([
+            b[3], b[2], b[1], b[0], b[5], b[4], b[7], b[6], b[8], b[9], b[10], b[11], b[12], b[13],
+            b[14], b[15],
+        ])
+    }
+}
+
 impl TryFrom<&[u8]> for PartUUID {
     type Error = GptError;
 
     fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
-        if value.len() != UUID_SIZE
-            || value
-                .iter()
-                .filter(|byte| (0x41..0x5A).contains(*byte) || (0x61..0x7A).contains(*byte))
-                .count()
-                < value.len()
-        {
-            return Err(GptError::PartUUID);
+        if value.len() != UUID_SIZE {
+            return Err(GptError::new(ErrorKind::PartUUID));
         }
         let mut uuid = [0; UUID_SIZE];
         uuid.copy_from_slice(value);
@@ -44,27 +103,17 @@ impl DerefMut for PartUUID {
 }
 
 impl Display for PartUUID {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let (left, right) = self.0.split_at(UUID_SIZE / 2);
+    /// Canonical 8-4-4-4-12 lowercase text, e.g. `c12a7328-f81f-11d2-ba4b-00a0c93ec93b`.
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let value = self.to_u128();
         write!(
             f,
-            "{:x}{:x}{:x}{:x}-{:x}{:x}-{:x}{:x}-{:x}{:x}-{:x}{:x}{:x}{:x}{:x}{:x}",
-            left[3],
-            left[2],
-            left[1],
-            left[0],
-            left[5],
-            left[4],
-            left[7],
-            left[6],
-            right[0],
-            right[1],
-            right[2],
-            right[3],
-            right[4],
-            right[5],
-            right[6],
-            right[7],
+            "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+            value >> 96,
+            (value >> 80) & 0xFFFF,
+            (value >> 64) & 0xFFFF,
+            (value >> 48) & 0xFFFF,
+            value & 0xFFFF_FFFF_FFFF,
         )
     }
 }
@@ -72,33 +121,189 @@ impl Display for PartUUID {
 impl FromStr for PartUUID {
     type Err = GptError;
 
+    /// Parse the canonical 8-4-4-4-12 text in upper or lower case, optionally enclosed in
+    /// braces or prefixed with `urn:uuid:`.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let chars = s.chars().filter(|ch| ch.ne(&'-')).collect::<Vec<char>>();
-        if chars.len() != UUID_SIZE {
-            return Err(GptError::PartUUID);
+        let text = match s.as_bytes() {
+            [b'{', inner @ .., b'}'] => inner,
+            bytes
+                if bytes.len() > URN_PREFIX.len()
+                    && bytes[..URN_PREFIX.len()].eq_ignore_ascii_case(URN_PREFIX) =>
+            {
+                &bytes[URN_PREFIX.len()..]
+            }
+            bytes => bytes,
+        };
+        if text.len() != GUID_TEXT_LEN {
+            return Err(GptError::new(ErrorKind::PartUUID));
         }
-        let mut uuid = [0; UUID_SIZE];
-        let (left, right) = chars.split_at(UUID_SIZE / 2);
-        uuid[0] = validate_guid_byte(left[3])?;
-        uuid[1] = validate_guid_byte(left[2])?;
-        uuid[2] = validate_guid_byte(left[1])?;
-        uuid[3] = validate_guid_byte(left[0])?;
-        uuid[4] = validate_guid_byte(left[5])?;
-        uuid[5] = validate_guid_byte(left[4])?;
-        uuid[6] = validate_guid_byte(left[7])?;
-        uuid[7] = validate_guid_byte(left[6])?;
-        for (index, ch) in right.iter().enumerate() {
-            uuid[index + UUID_SIZE / 2] = validate_guid_byte(*ch)?;
+        let mut value = 0u128;
+        for (index, ch) in text.iter().enumerate() {
+            if GUID_TEXT_HYPHENS.contains(&index) {
+                if *ch != b'-' {
+                    return Err(GptError::new(ErrorKind::PartUUID));
+                }
+                continue;
+            }
+            let digit = (*ch as char)
+                .to_digit(16)
+                .ok_or(GptError::new(ErrorKind::PartUUID))?;
+            value = value << 4 | digit as u128;
         }
-        Ok(Self(uuid))
+        Ok(Self::from_u128(value))
+    }
+}
+
+/// A source of random bytes for [`PartUUID::new_v4_with`], e.g. a hardware RNG in firmware.
+pub trait GuidRng {
+    fn fill_bytes(&mut self, buf: &mut [u8]) -> Result<(), GptError>;
+}
+
+/// The random number generator of the operating system.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsRng;
+
+#[cfg(feature = "std")]
+impl GuidRng for OsRng {
+    fn fill_bytes(&mut self, buf: &mut [u8]) -> Result<(), GptError> {
+        getrandom::fill(buf).map_err(|_| GptError::new(ErrorKind::Rng))
     }
 }
 
-fn validate_guid_byte(ch: char) -> Result<u8, GptError> {
-    let byte = ch as u8;
-    if (0x41..0x5A).contains(&byte) || (0x61..0x7A).contains(&byte) {
-        Ok(byte)
-    } else {
-        Err(GptError::PartUUID)
+#[cfg(feature = "uuid")]
+impl From<::uuid::Uuid> for PartUUID {
+    fn from(value: ::uuid::Uuid) -> Self {
+        Self(value.to_bytes_le())
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl From<PartUUID> for ::uuid::Uuid {
+    fn from(value: PartUUID) -> Self {
+        Self::from_bytes_le(value.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloc::string::ToString;
+
+    const ESP: &str = "c12a7328-f81f-11d2-ba4b-00a0c93ec93b";
+    /// The EFI System partition type GUID as stored on disk.
+    const ESP_BYTES: [u8; UUID_SIZE] = [
+        0x28, 0x73, 0x2A, 0xC1, 0x1F, 0xF8, 0xD2, 0x11, 0xBA, 0x4B, 0x00, 0xA0, 0xC9, 0x3E, 0xC9,
+        0x3B,
+    ];
+
+    #[test]
+    fn mixed_endian_layout() {
+        let guid = PartUUID::from_u128(0xC12A7328_F81F_11D2_BA4B_00A0C93EC93B);
+        assert_eq!(*guid, ESP_BYTES);
+        assert_eq!(guid.to_u128(), 0xC12A7328_F81F_11D2_BA4B_00A0C93EC93B);
+        assert_eq!(PartUUID::try_from(&ESP_BYTES[..]).unwrap(), guid);
+        assert!(PartUUID::try_from(&ESP_BYTES[1..]).is_err());
+    }
+
+    #[test]
+    fn parse_text_forms() {
+        let guid = PartUUID::try_from(&ESP_BYTES[..]).unwrap();
+        for text in [
+            ESP,
+            "C12A7328-F81F-11D2-BA4B-00A0C93EC93B",
+            "{c12a7328-f81f-11d2-ba4b-00a0c93ec93b}",
+            "urn:uuid:c12a7328-f81f-11d2-ba4b-00a0c93ec93b",
+            "URN:UUID:C12A7328-f81f-11d2-ba4b-00a0c93ec93b",
+        ] {
+            assert_eq!(text.parse::<PartUUID>().unwrap(), guid, "{}", text);
+        }
+    }
+
+    #[test]
+    fn reject_invalid_text() {
+        for text in [
+            "",
+            "c12a7328f81f11d2ba4b00a0c93ec93b",
+            "c12a7328-f81f-11d2-ba4b-00a0c93ec93",
+            "c12a7328-f81f-11d2-ba4b-00a0c93ec93bb",
+            "c12a7328_f81f-11d2-ba4b-00a0c93ec93b",
+            "g12a7328-f81f-11d2-ba4b-00a0c93ec93b",
+            "{c12a7328-f81f-11d2-ba4b-00a0c93ec93b",
+            "urn:uuid:",
+        ] {
+            let err = text.parse::<PartUUID>().err().unwrap();
+            assert_eq!(err.kind(), ErrorKind::PartUUID, "{}", text);
+        }
+    }
+
+    #[test]
+    fn display_keeps_leading_zeros() {
+        let guid = PartUUID::try_from(&ESP_BYTES[..]).unwrap();
+        assert_eq!(guid.to_string(), ESP);
+        assert_eq!(
+            PartUUID::from_u128(0x00000001_0002_0003_0004_000000000005).to_string(),
+            "00000001-0002-0003-0004-000000000005"
+        );
+        assert_eq!(
+            PartUUID::default().to_string(),
+            "00000000-0000-0000-0000-000000000000"
+        );
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn uuid_interop() {
+        let uuid = ::uuid::Uuid::parse_str(ESP).unwrap();
+        let guid = PartUUID::from(uuid);
+        assert_eq!(*guid, ESP_BYTES);
+        assert_eq!(::uuid::Uuid::from(guid), uuid);
+    }
+
+    /// Fills every buffer with the same byte.
+    struct ConstRng(u8);
+
+    impl GuidRng for ConstRng {
+        fn fill_bytes(&mut self, buf: &mut [u8]) -> Result<(), GptError> {
+            buf.fill(self.0);
+            Ok(())
+        }
+    }
+
+    struct FailingRng;
+
+    impl GuidRng for FailingRng {
+        fn fill_bytes(&mut self, _: &mut [u8]) -> Result<(), GptError> {
+            Err(GptError::new(ErrorKind::Rng))
+        }
+    }
+
+    #[test]
+    fn v4_version_and_variant_bits() {
+        // The version is the high nibble of time_hi_and_version, stored little-endian in bytes 6
+        // and 7, the variant the two high bits of byte 8.
+        let ones = PartUUID::new_v4_with(&mut ConstRng(0xFF)).unwrap();
+        assert_eq!(ones.to_string(), "ffffffff-ffff-4fff-bfff-ffffffffffff");
+        assert_eq!((ones[7], ones[8]), (0x4F, 0xBF));
+
+        let zeros = PartUUID::new_v4_with(&mut ConstRng(0)).unwrap();
+        assert_eq!(zeros.to_string(), "00000000-0000-4000-8000-000000000000");
+        assert_eq!((zeros[7], zeros[8]), (0x40, 0x80));
+        assert_eq!(zeros.version(), 4);
+    }
+
+    #[test]
+    fn v4_rng_error() {
+        let err = PartUUID::new_v4_with(&mut FailingRng).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::Rng);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn v4_from_os_rng() {
+        let guid = PartUUID::new_v4().unwrap();
+        assert_eq!(guid.version(), 4);
+        assert_eq!(guid[8] & 0xC0, 0x80);
+        assert_ne!(guid, PartUUID::new_v4().unwrap());
     }
 }